@@ -14,6 +14,7 @@ fn create_tool_schema_const_indentifier(struct_name: &str) -> Ident {
     )
 }
 
+#[derive(Clone)]
 struct FunctionDefintion {
     is_async: bool,
     name: Ident,
@@ -22,6 +23,18 @@ struct FunctionDefintion {
     return_type: ReturnType,
     // option because, late, but required
     description: Option<String>,
+    // `#[cfg(..)]` attributes on the method, applied to everything generated for it
+    cfg_attrs: Vec<syn::Attribute>,
+    // description of what the function returns
+    returns: Option<String>,
+    // the output is converted to a `String`, since it is string like e.g. `Arc<str>` or the tool has `text_output`
+    output_to_string: bool,
+    // the function takes `self`, otherwise it is an associated function called with `Self::`
+    has_receiver: bool,
+    // calling the function more than once has the same effect as calling it once
+    idempotent: bool,
+    // the parameters are the schema of the single parameter, rather than an object with it as a property
+    flatten: bool,
 }
 
 impl FunctionDefintion {
@@ -30,47 +43,188 @@ impl FunctionDefintion {
             &format!(
                 "_{}_{}_PARMETER_SCHEMA",
                 struct_name.to_uppercase(),
-                self.name_str.to_uppercase()
+                self.name.to_string().to_uppercase()
             ),
             Span::call_site(),
         )
     }
 }
 
+#[derive(Clone)]
 struct Parameter {
     name: Ident,
     name_str: String,
+    // the key of the parameter in the arguments and schema, set with `#[tool_param(rename = "...")]`
+    json_name: String,
     param_type: syn::Type,
     // option because, late, but required
     description: Option<String>,
+    // accept loosely typed values e.g. `"true"` for a `bool`, set with `#[tool_param(coerce)]`
+    coerce: bool,
+    // json schema keywords e.g. `"minimum": 0`, set with `#[tool_param(min = 0)]`
+    constraints: Vec<TokenStream>,
+    // the value used when the parameter is missing, set with `#[tool_param(default = 10)]`
+    default: Option<syn::Expr>,
 }
 
+#[derive(Clone)]
 enum ReturnType {
     Result(ResultReturnType),
     Other(OtherReturnType),
 }
 
+#[derive(Clone)]
 struct ResultReturnType {
     okay: Type,
     error: Type,
 }
 
+#[derive(Clone)]
 struct OtherReturnType {
     other: Type,
 }
 
+impl ReturnType {
+    /// The type of a successful call
+    fn okay_type(&self) -> &Type {
+        match self {
+            ReturnType::Result(result_return_type) => &result_return_type.okay,
+            ReturnType::Other(other_return_type) => &other_return_type.other,
+        }
+    }
+
+    fn okay_type_mut(&mut self) -> &mut Type {
+        match self {
+            ReturnType::Result(result_return_type) => &mut result_return_type.okay,
+            ReturnType::Other(other_return_type) => &mut other_return_type.other,
+        }
+    }
+}
+
+/// Options passed to the macro e.g. `#[tool(json_output)]`
+#[derive(Default)]
+struct ToolAttributes {
+    /// Generate `Tool::output_to_json` by serializing the concrete return type of each function
+    json_output: bool,
+    /// The group the tool belongs to e.g. "filesystem"
+    group: Option<String>,
+    /// Convert the output of every function to a `String` with `Display`
+    text_output: bool,
+    /// The doc comment of the impl block, this is not an option of the macro
+    description: Option<String>,
+    /// The error type all function errors are converted into with `From` e.g. `#[tool(error = MyError)]`
+    error: Option<Type>,
+    /// Prefix of every function name e.g. `fs` exposes `search` as `fs__search`. Joined with `__`
+    /// since providers do not allow `.` in function names
+    namespace: Option<String>,
+    /// A boxed trait object every output is also converted into e.g. `#[tool(output = Box<dyn Summary>)]`
+    output: Option<Type>,
+}
+
+/// Options passed to the method attribute e.g. `#[tool_part(returns = "...")]`
+#[derive(Default)]
+struct ToolPartAttributes {
+    /// Description of what the function returns
+    returns: Option<String>,
+    /// The function is safe to retry
+    idempotent: bool,
+    /// The name the function is exposed as, instead of the Rust function name
+    name: Option<syn::LitStr>,
+    /// The single parameter is the parameters
+    flatten: bool,
+    /// Description of the function, instead of the doc comment
+    description: Option<String>,
+}
+
+impl ToolPartAttributes {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("returns") {
+            self.returns = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("idempotent") {
+            self.idempotent = true;
+            Ok(())
+        } else if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse::<syn::LitStr>()?);
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            self.description = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("flatten") {
+            self.flatten = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported tool_part attribute"))
+        }
+    }
+}
+
+impl ToolAttributes {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("json_output") {
+            self.json_output = true;
+            Ok(())
+        } else if meta.path.is_ident("text_output") {
+            self.text_output = true;
+            Ok(())
+        } else if meta.path.is_ident("namespace") {
+            let namespace = meta.value()?.parse::<syn::LitStr>()?;
+            // OpenAI and Anthropic only accept function names matching `^[a-zA-Z0-9_-]+$`
+            if namespace.value().is_empty()
+                || !namespace.value().chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(syn::Error::new_spanned(
+                    namespace,
+                    "A namespace can only contain ascii letters, digits, `_` and `-`, since llm \
+                    providers reject other characters in function names.",
+                ));
+            }
+            self.namespace = Some(namespace.value());
+            Ok(())
+        } else if meta.path.is_ident("group") {
+            self.group = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("error") {
+            self.error = Some(meta.value()?.parse::<Type>()?);
+            Ok(())
+        } else if meta.path.is_ident("output") {
+            self.output = Some(meta.value()?.parse::<Type>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported tool attribute"))
+        }
+    }
+}
+
 #[proc_macro_attribute]
 pub fn tool(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let mut tool_attributes = ToolAttributes::default();
+    let tool_attributes_parser = syn::meta::parser(|meta| tool_attributes.parse(meta));
+    parse_macro_input!(attr with tool_attributes_parser);
     let mut input = parse_macro_input!(item as ItemImpl);
+    if let Some((_, trait_path, _)) = &input.trait_ {
+        return syn::Error::new_spanned(
+            trait_path,
+            "`#[tool]` is not supported on a trait impl, since the default methods of the trait are not \
+            visible to the macro. Add `#[tool]` to an inherent impl block whose `#[tool_part]` methods call \
+            the trait methods instead.",
+        )
+        .into_compile_error()
+        .into();
+    }
     let struct_name = match &*input.self_ty {
             Type::Path(type_path) => &type_path.path.segments.last().unwrap().ident,
             _ => panic!("Invalid impl type"),
         };
     let generics = &input.generics;
     let struct_name_str = struct_name.to_token_stream().to_string();
+    let description_lines: Vec<String> = doc_lines(&input.attrs).map(|(_, line)| line.trim().to_owned()).collect();
+    if !description_lines.is_empty() {
+        tool_attributes.description = Some(description_lines.join("\n"));
+    }
     
     let methods: Vec<_> = input
         .items
@@ -99,6 +253,11 @@ pub fn tool(
                 method.attrs.retain(|attr|{
                     !attr.path().is_ident("tool_part")
                 });
+                for input in method.sig.inputs.iter_mut() {
+                    if let FnArg::Typed(arg) = input {
+                        arg.attrs.retain(|attr| !attr.path().is_ident("tool_param"));
+                    }
+                }
             }
         });
 
@@ -116,13 +275,55 @@ pub fn tool(
             Ok(okay) => okay,
             Err(error) => return error.into_compile_error().into(),
         };
+        function_definition.cfg_attrs = attrs.iter().filter(|attr| attr.path().is_ident("cfg")).cloned().collect();
+        let mut tool_part_attributes = ToolPartAttributes::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool_part")) {
+            if let syn::Meta::List(_) = &attr.meta {
+                if let Err(error) = attr.parse_nested_meta(|meta| tool_part_attributes.parse(meta)) {
+                    return error.into_compile_error().into();
+                }
+            }
+        }
+        function_definition.returns = tool_part_attributes.returns;
+        function_definition.description = tool_part_attributes.description;
+        function_definition.idempotent = tool_part_attributes.idempotent;
+        if let Some(name) = tool_part_attributes.name {
+            function_definition.name_str = name.value();
+        }
+        if tool_part_attributes.flatten {
+            if let Err(error) = check_flatten(&function_definition) {
+                return error.into_compile_error().into();
+            }
+            function_definition.flatten = true;
+        }
         match extract_description(&mut function_definition, attrs) {
             Ok(_) => {}
             Err(error) => return error.into_compile_error().into(),
         }
+        if tool_attributes.text_output {
+            function_definition.output_to_string = true;
+            *function_definition.return_type.okay_type_mut() = syn::parse_quote!(String);
+        }
         function_definitions.push(function_definition);
     }
 
+    if let Some(namespace) = &tool_attributes.namespace {
+        for function_definition in function_definitions.iter_mut() {
+            function_definition.name_str = format!("{namespace}__{}", function_definition.name_str);
+        }
+    }
+
+    for (index, function_definition) in function_definitions.iter().enumerate() {
+        if let Some(other) = function_definitions[..index].iter().find(|other| other.name_str == function_definition.name_str) {
+            return syn::Error::new_spanned(
+                &function_definition.name,
+                format!("`{}` and `{}` are both exposed as `{}`", other.name, function_definition.name, function_definition.name_str),
+            )
+            .into_compile_error()
+            .into();
+        }
+    }
+
     if function_definitions.is_empty() {
         return syn::Error::new_spanned(
             struct_name,
@@ -132,12 +333,12 @@ pub fn tool(
         .into();
     }
 
-    let function_schema = create_tool_json_schema(&struct_name_str, &mut function_definitions);
+    let function_schema = create_tool_json_schema(&struct_name_str, &mut function_definitions, &tool_attributes);
     let parameter_json_schema = function_definitions.iter_mut().map(|function_definition| {
         create_function_parameter_json_schema(&struct_name_str, function_definition)
     }).fold(TokenStream::new(), |mut acc, item| { acc.append_all(item); acc });
 
-    let impl_traits = impl_traits(&struct_name, &struct_name_str, generics, &function_definitions);
+    let impl_traits = impl_traits(&struct_name, &struct_name_str, generics, &function_definitions, &tool_attributes);
 
     let expanded = quote! {
         #input
@@ -166,7 +367,82 @@ impl<'a> CommonReturnTypes<'a> {
     }
 }
 
-fn impl_traits(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::Generics, function_definitions: &Vec<FunctionDefintion>) -> TokenStream {
+/// The most distinct `#[cfg(..)]` predicates on the methods of a tool, since the impls are
+/// generated for every combination of them
+const MAX_CFG_PREDICATES: usize = 6;
+
+fn impl_traits(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::Generics, function_definitions: &Vec<FunctionDefintion>, tool_attributes: &ToolAttributes) -> TokenStream {
+    // The macro sees every method, even those a `#[cfg(..)]` removes, so which return types are
+    // common depends on the cfg. The impls are generated for each combination of the predicates,
+    // with only the methods active in that combination, and gated on it.
+    let mut predicates: Vec<TokenStream> = Vec::new();
+    for function_definition in function_definitions {
+        if let Some(predicate) = cfg_predicate(function_definition) {
+            if !predicates.iter().any(|existing| existing.to_string() == predicate.to_string()) {
+                predicates.push(predicate);
+            }
+        }
+    }
+    if predicates.is_empty() {
+        return impl_traits_for(struct_name, struct_name_str, generics, function_definitions, tool_attributes);
+    }
+    if predicates.len() > MAX_CFG_PREDICATES {
+        return syn::Error::new(
+            Span::call_site(),
+            format!("`#[tool_part]` methods may only use up to {MAX_CFG_PREDICATES} distinct `#[cfg(..)]` predicates"),
+        )
+        .into_compile_error();
+    }
+    let mut all_impl_tokens = TokenStream::new();
+    for active in 0..(1usize << predicates.len()) {
+        let is_active = |index: usize| active & (1 << index) != 0;
+        let active_function_definitions: Vec<FunctionDefintion> = function_definitions
+            .iter()
+            .filter(|function_definition| match cfg_predicate(function_definition) {
+                Some(predicate) => {
+                    let index = predicates.iter().position(|existing| existing.to_string() == predicate.to_string()).unwrap();
+                    is_active(index)
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+        let conditions = predicates.iter().enumerate().map(|(index, predicate)| {
+            if is_active(index) {
+                quote! { #predicate }
+            } else {
+                quote! { not(#predicate) }
+            }
+        });
+        let impl_tokens = impl_traits_for(struct_name, struct_name_str, generics, &active_function_definitions, tool_attributes);
+        all_impl_tokens.append_all(quote! {
+            #[cfg(all(#(#conditions),*))]
+            const _: () = {
+                #impl_tokens
+            };
+        });
+    }
+    all_impl_tokens
+}
+
+/// The predicate of the `#[cfg(..)]` attributes of a method, if any
+fn cfg_predicate(function_definition: &FunctionDefintion) -> Option<TokenStream> {
+    let predicates: Vec<TokenStream> = function_definition
+        .cfg_attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::List(list) => Some(list.tokens.clone()),
+            _ => None,
+        })
+        .collect();
+    match predicates.len() {
+        0 => None,
+        1 => predicates.into_iter().next(),
+        _ => Some(quote! { all(#(#predicates),*) }),
+    }
+}
+
+fn impl_traits_for(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::Generics, function_definitions: &Vec<FunctionDefintion>, tool_attributes: &ToolAttributes) -> TokenStream {
     let mut common_return_types = CommonReturnTypes::new();
     for function_definition in function_definitions.iter() {
         match &function_definition.return_type {
@@ -182,7 +458,11 @@ fn impl_traits(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::
 
     let mut common_err_type: Option<Type> = None;
     let all_are_results_with_same_err_type = common_return_types.result_err.len() == 1;
-    if all_are_results_with_same_err_type {
+    if let (Some(error_type), false) = (&tool_attributes.error, common_return_types.result_err.is_empty()) {
+        // every error is converted into the declared error type
+        common_err_type = Some(error_type.clone());
+    }
+    else if all_are_results_with_same_err_type {
         let first = *common_return_types.result_err.iter().next().unwrap();
         common_err_type = Some(first.clone());
     }
@@ -190,10 +470,22 @@ fn impl_traits(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::
     let all_have_same_ok_type = common_return_types.result_ok_and_regular.len() == 1;
     if all_have_same_ok_type {
         let first = *common_return_types.result_ok_and_regular.iter().next().unwrap();
-        common_ok_type = Some(first.clone());
+        // already the boxed type, so would be the same impl as the boxed one
+        if !is_box_dyn_any(first) {
+            common_ok_type = Some(first.clone());
+        }
     }
 
     let all_functions_are_regular = common_return_types.result_err.len() == 0; // aka no result functions
+    // the output type is already a common output type, so would be the same impl
+    let output_type = tool_attributes.output.as_ref().filter(|output| {
+        common_ok_type.as_ref().is_none_or(|ok_type| ok_type.to_token_stream().to_string() != output.to_token_stream().to_string())
+    }).map(|output| output.to_token_stream());
+    let output_err_types: Vec<(bool, TokenStream)> = match (&common_err_type, all_functions_are_regular) {
+        (Some(err_type), _) => vec![(false, err_type.to_token_stream())],
+        (None, true) => vec![(false, quote! { std::convert::Infallible })],
+        (None, false) => vec![],
+    };
     let impls_needed = determine_impls_needed(common_ok_type, common_err_type, all_functions_are_regular);
 
     let mut all_impl_tokens = TokenStream::new();
@@ -209,15 +501,21 @@ fn impl_traits(struct_name: &syn::Ident, struct_name_str: &str, generics: &syn::
     };
     for impl_needed in impls_needed {
         let tokens = match impl_needed {
-            ImplTypes::BoxAndBox => impl_trait(struct_name, struct_name_str, generics,function_definitions, true, true, &box_any_type, &box_error_type),
-            ImplTypes::BoxAndSpecific(err_type) => impl_trait(struct_name, struct_name_str, generics,function_definitions, true, false, &box_any_type, &err_type.to_token_stream()),
-            ImplTypes::SpecificAndBox(ok_type) => impl_trait(struct_name, struct_name_str, generics,function_definitions, false, true, &ok_type.to_token_stream(), &box_error_type),
-            ImplTypes::SpecificAndSpecific(ok_type, err_type) => impl_trait(struct_name, struct_name_str, generics,function_definitions, false, false, &ok_type.to_token_stream(), &err_type.to_token_stream()),
-            ImplTypes::BoxAndInfallible => impl_trait(struct_name, struct_name_str, generics,function_definitions, true, false, &box_any_type, &infallible_type),
-            ImplTypes::SpecificAndInfallible(ok_type) => impl_trait(struct_name, struct_name_str, generics,function_definitions, false, false, &ok_type.to_token_stream(), &infallible_type),
+            ImplTypes::BoxAndBox => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, true, true, &box_any_type, &box_error_type),
+            ImplTypes::BoxAndSpecific(err_type) => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, true, false, &box_any_type, &err_type.to_token_stream()),
+            ImplTypes::SpecificAndBox(ok_type) => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, false, true, &ok_type.to_token_stream(), &box_error_type),
+            ImplTypes::SpecificAndSpecific(ok_type, err_type) => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, false, false, &ok_type.to_token_stream(), &err_type.to_token_stream()),
+            ImplTypes::BoxAndInfallible => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, true, false, &box_any_type, &infallible_type),
+            ImplTypes::SpecificAndInfallible(ok_type) => impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, false, false, &ok_type.to_token_stream(), &infallible_type),
         };
         all_impl_tokens.append_all(tokens);
     }
+    if let Some(output_type) = output_type {
+        all_impl_tokens.append_all(impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, true, true, &output_type, &box_error_type));
+        for (err_needs_box, err_type) in output_err_types {
+            all_impl_tokens.append_all(impl_trait(struct_name, struct_name_str, generics, function_definitions, tool_attributes, true, err_needs_box, &output_type, &err_type));
+        }
+    }
 
     all_impl_tokens
 }
@@ -249,57 +547,164 @@ fn determine_impls_needed(common_ok_type: Option<Type>, common_err_type: Option<
     vecs
 }
 
-fn impl_trait(struct_name: &syn::Ident, struct_name_str:&str, generics: &syn::Generics, function_definitions: &Vec<FunctionDefintion>, ok_needs_box: bool, err_needs_box: bool, ok_type: &TokenStream, err_type: &TokenStream) -> TokenStream {
+fn impl_trait(struct_name: &syn::Ident, struct_name_str:&str, generics: &syn::Generics, function_definitions: &Vec<FunctionDefintion>, tool_attributes: &ToolAttributes, ok_needs_box: bool, err_needs_box: bool, ok_type: &TokenStream, err_type: &TokenStream) -> TokenStream {
     let function_names = function_definitions.iter().map(|function_definition| {
-        &function_definition.name_str
+        let cfg_attrs = &function_definition.cfg_attrs;
+        let name_str = &function_definition.name_str;
+        quote! {
+            #(#cfg_attrs)* #name_str
+        }
+    });
+
+    let descriptions = function_definitions.iter().map(|function_definition| {
+        let cfg_attrs = &function_definition.cfg_attrs;
+        let description = function_definition.description.as_deref().unwrap_or_default();
+        quote! {
+            #(#cfg_attrs)* #description
+        }
     });
 
     let run_arms = function_definitions.iter().map(|function_definition| {
-        let function_parameter_statements = function_definition.parameters.iter().map(|parameter|{
+        let flatten_statement = function_definition.flatten.then(|| {
+            let Parameter { name, param_type, .. } = &function_definition.parameters[0];
+            let serde_message = format!("The parameters of function `{}` do not follow schema", function_definition.name_str);
+            let (owned_type, borrow) = match param_type {
+                Type::Reference(type_reference) => (&*type_reference.elem, Some(quote! { let #name: #param_type = &#name; })),
+                param_type => (param_type, None),
+            };
+            quote! {
+                let #name: #owned_type = serde_json::from_value::<#owned_type>(serde_json::Value::Object(parameters)).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?;
+                #borrow
+            }
+        });
+        let function_parameter_statements = function_definition.parameters.iter().filter(|_| !function_definition.flatten).map(|parameter|{
             let Parameter {
                 name,
-                name_str,
+                name_str: _,
+                json_name,
                 param_type,
                 description: _,
+                coerce,
+                constraints: _,
+                default,
             } = parameter;
-            let serde_message = format!("Parameter `{}` does not follow schema", name_str);
-            let missing_message = format!("Missing `{}` parameter", name_str);
+            let function_name_str = &function_definition.name_str;
+            let serde_message = format!("Parameter `{}` of function `{}` does not follow schema", json_name, function_name_str);
+            let missing_message = format!("Missing `{}` parameter of function `{}`", json_name, function_name_str);
             let deserialize= match param_type {
                 Type::Reference(type_reference) => match &*type_reference.elem {
                     Type::Path(type_path) => {
                         if type_path.path.get_ident().is_some_and(|item| &*item.to_string() == "str") {
                             Some(quote! {
-                                let #name: &str = &*serde_json::from_value::<String>(#name).map_err(|_| llmtoolbox::FunctionCallError::parsing(#serde_message.to_owned()))?;
+                                let #name: &str = &*serde_json::from_value::<String>(#name).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?;
                             })
                         }
                         else {
                             Some(quote! {
-                                let #name: #param_type = &serde_json::from_value::<#type_path>(#name).map_err(|_| llmtoolbox::FunctionCallError::parsing(#serde_message.to_owned()))?;
+                                let #name: #param_type = &serde_json::from_value::<#type_path>(#name).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?;
                             })
                         }
                     },
+                    Type::Slice(type_slice) => {
+                        let item_type = &type_slice.elem;
+                        Some(quote! {
+                            let #name: #param_type = &serde_json::from_value::<Vec<#item_type>>(#name).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?;
+                        })
+                    },
                     _ => None,
                 },
                 _ => None,
             }.unwrap_or(quote! {
-                let #name: #param_type = serde_json::from_value::<#param_type>(#name).map_err(|_| llmtoolbox::FunctionCallError::parsing(#serde_message.to_owned()))?;
+                let #name: #param_type = serde_json::from_value::<#param_type>(#name).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?;
             });
+            let deserialize = if *coerce {
+                quote! {
+                    let #name: bool = llmtoolbox::__private::coerce_bool(&#name).ok_or_else(|| llmtoolbox::FunctionCallError::parsing(format!("{}: expected a bool, \"true\"/\"false\" or 0/1, got {}", #serde_message, #name)))?;
+                }
+            } else {
+                deserialize
+            };
+            let remove = match default {
+                Some(default) => quote! { parameters.remove(#json_name).or_else(|| Some(serde_json::json!(#default))) },
+                None => quote! { parameters.remove(#json_name) },
+            };
+            if let Some(inner_type) = option_inner_type(param_type) {
+                // a missing parameter is `None` unless it has a default, references are borrowed from an owned `Option`
+                let (owned_type, borrow) = match inner_type {
+                    Type::Reference(type_reference) => match &*type_reference.elem {
+                        Type::Path(type_path) if type_path.path.is_ident("str") => (quote! { Option<String> }, Some(quote! { .as_deref() })),
+                        Type::Slice(type_slice) => {
+                            let item_type = &type_slice.elem;
+                            (quote! { Option<Vec<#item_type>> }, Some(quote! { .as_deref() }))
+                        },
+                        elem => (quote! { Option<#elem> }, Some(quote! { .as_ref() })),
+                    },
+                    _ => (quote! { #param_type }, None),
+                };
+                // `null` is `Some(None)` when the option is nested
+                let deserialize = if option_inner_type(inner_type).is_some() {
+                    quote! { Some(serde_json::from_value::<#inner_type>(value).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))?) }
+                } else {
+                    quote! { serde_json::from_value::<#owned_type>(value).map_err(|error| llmtoolbox::FunctionCallError::parsing(format!("{}: {}", #serde_message, error)))? }
+                };
+                let borrow = borrow.map(|borrow| quote! {
+                    let #name: #param_type = #name #borrow;
+                });
+                return quote! {
+                    let #name: #owned_type = match #remove {
+                        Some(value) => #deserialize,
+                        None => None,
+                    };
+                    #borrow
+                };
+            }
             quote! {
-                let #name = parameters.remove(#name_str).ok_or_else(|| llmtoolbox::FunctionCallError::parsing(#missing_message.to_owned()))?;
+                let #name = #remove.ok_or_else(|| llmtoolbox::FunctionCallError::parsing(#missing_message.to_owned()))?;
                 #deserialize
             }
         });
         let return_statement = 
-        make_return_statement(function_definition, ok_needs_box, err_needs_box);
+        make_return_statement(function_definition, tool_attributes, ok_needs_box, err_needs_box, ok_type);
         let function_name_str = &function_definition.name_str;
+        let cfg_attrs = &function_definition.cfg_attrs;
         quote! {
+            #(#cfg_attrs)*
             #function_name_str => {
+                    #flatten_statement
                     #(#function_parameter_statements)*
                     #return_statement
                 }
         }
     }).fold(TokenStream::new(), |mut acc, item| { acc.append_all(item); acc });
 
+    let returns_description = make_returns_description(function_definitions);
+    let is_idempotent = make_is_idempotent(function_definitions);
+
+    let tool_description = match &tool_attributes.description {
+        Some(description) => quote! {
+            fn tool_description(&self) -> Option<&'static str> {
+                Some(#description)
+            }
+        },
+        None => quote! {},
+    };
+    let group = match &tool_attributes.group {
+        Some(group) => quote! {
+            fn group(&self) -> Option<&'static str> {
+                Some(#group)
+            }
+        },
+        None => quote! {},
+    };
+
+    // the output is a boxed trait object that cannot be downcast to serialize it
+    let is_custom_output = tool_attributes.output.as_ref().is_some_and(|output| output.to_token_stream().to_string() == ok_type.to_string());
+    let output_to_json = if tool_attributes.json_output && !is_custom_output {
+        make_output_to_json(function_definitions, ok_needs_box, ok_type)
+    } else {
+        quote! {}
+    };
+
     let schema = create_tool_schema_const_indentifier(struct_name_str);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
@@ -311,10 +716,26 @@ fn impl_trait(struct_name: &syn::Ident, struct_name_str:&str, generics: &syn::Ge
                 ]
             }
 
+            fn descriptions(&self) -> &[&'static str] {
+                &[
+                    #(#descriptions),*
+                ]
+            }
+
             fn schema(&self) -> &'static serde_json::Map<String, serde_json::Value> {
                 #schema.as_object().unwrap()
             }
 
+            #returns_description
+
+            #is_idempotent
+
+            #group
+
+            #tool_description
+
+            #output_to_json
+
             fn call_function<'life0, 'life1, 'async_trait>(
                 &'life0 self,
                 name: &'life1 str,
@@ -376,7 +797,84 @@ fn impl_trait(struct_name: &syn::Ident, struct_name_str:&str, generics: &syn::Ge
     }
 }
 
-fn make_return_statement(function_definition: &FunctionDefintion, ok_needs_box: bool, err_needs_box: bool) -> TokenStream {
+fn make_returns_description(function_definitions: &Vec<FunctionDefintion>) -> TokenStream {
+    let returns_arms = function_definitions.iter().filter_map(|function_definition| {
+        let returns = function_definition.returns.as_ref()?;
+        let function_name_str = &function_definition.name_str;
+        let cfg_attrs = &function_definition.cfg_attrs;
+        Some(quote! {
+            #(#cfg_attrs)*
+            #function_name_str => Some(#returns),
+        })
+    }).collect::<Vec<_>>();
+    if returns_arms.is_empty() {
+        return quote! {};
+    }
+    quote! {
+        fn returns_description(&self, function_name: &str) -> Option<&'static str> {
+            match function_name {
+                #(#returns_arms)*
+                _ => None,
+            }
+        }
+    }
+}
+
+fn make_is_idempotent(function_definitions: &Vec<FunctionDefintion>) -> TokenStream {
+    let idempotent_names = function_definitions.iter().filter(|function_definition| function_definition.idempotent).map(|function_definition| {
+        let function_name_str = &function_definition.name_str;
+        let cfg_attrs = &function_definition.cfg_attrs;
+        quote! {
+            #(#cfg_attrs)*
+            #function_name_str => true,
+        }
+    }).collect::<Vec<_>>();
+    if idempotent_names.is_empty() {
+        return quote! {};
+    }
+    quote! {
+        fn is_idempotent(&self, function_name: &str) -> bool {
+            match function_name {
+                #(#idempotent_names)*
+                _ => false,
+            }
+        }
+    }
+}
+
+fn make_output_to_json(function_definitions: &Vec<FunctionDefintion>, ok_needs_box: bool, ok_type: &TokenStream) -> TokenStream {
+    let serialize = if ok_needs_box {
+        let serialize_arms = function_definitions.iter().map(|function_definition| {
+            let function_name_str = &function_definition.name_str;
+            let okay_type = function_definition.return_type.okay_type();
+            let cfg_attrs = &function_definition.cfg_attrs;
+            let mismatch_message = format!("The output is not of type `{}`", okay_type.to_token_stream());
+            quote! {
+                #(#cfg_attrs)*
+                #function_name_str => serde_json::to_value((**output).downcast_ref::<#okay_type>().ok_or_else(|| llmtoolbox::FunctionCallError::output_serialization(function_name.to_owned(), #mismatch_message.to_owned()))?),
+            }
+        });
+        quote! {
+            match function_name {
+                #(#serialize_arms)*
+                _ => return Err(llmtoolbox::FunctionCallError::function_not_found(function_name.to_owned())),
+            }
+        }
+    }
+    else {
+        quote! {
+            serde_json::to_value(output)
+        }
+    };
+    quote! {
+        fn output_to_json(&self, function_name: &str, output: &#ok_type) -> Result<serde_json::Value, llmtoolbox::FunctionCallError> {
+            let json = #serialize;
+            json.map_err(|error| llmtoolbox::FunctionCallError::output_serialization(function_name.to_owned(), error.to_string()))
+        }
+    }
+}
+
+fn make_return_statement(function_definition: &FunctionDefintion, tool_attributes: &ToolAttributes, ok_needs_box: bool, err_needs_box: bool, ok_type: &TokenStream) -> TokenStream {
     let async_part;
     if function_definition.is_async {
         async_part = quote! {
@@ -390,32 +888,58 @@ fn make_return_statement(function_definition: &FunctionDefintion, ok_needs_box:
         &parameter.name
     });
     let function_name = &function_definition.name;
+    let receiver = if function_definition.has_receiver {
+        quote! { self. }
+    } else {
+        quote! { Self:: }
+    };
+    let mut call = quote! {
+        #receiver #function_name(#(#function_parameters),*)#async_part
+    };
+    if function_definition.output_to_string {
+        call = match function_definition.return_type {
+            ReturnType::Result(_) => quote! { #call.map(|value| value.to_string()) },
+            ReturnType::Other(_) => quote! { #call.to_string() },
+        };
+    }
+    if let (Some(error_type), ReturnType::Result(_)) = (&tool_attributes.error, &function_definition.return_type) {
+        call = quote! { #call.map_err(<#error_type>::from) };
+    }
+    // the function already erases its output type
+    let okay_type = function_definition.return_type.okay_type();
+    let is_custom_output = tool_attributes.output.as_ref().is_some_and(|output| output.to_token_stream().to_string() == ok_type.to_string());
+    let already_erased = if is_custom_output {
+        okay_type.to_token_stream().to_string() == ok_type.to_string()
+    } else {
+        is_box_dyn_any(okay_type)
+    };
+    let ok_needs_box = ok_needs_box && !already_erased;
     match function_definition.return_type {
         ReturnType::Result(_) => {
             if ok_needs_box {
                 if err_needs_box {
                     quote! {
-                        return Ok(match self.#function_name(#(#function_parameters),*)#async_part {
-                            Ok(value) => Ok(Box::new(value) as Box<dyn std::any::Any>),
-                            Err(value) => Err(Box::new(value) as Box<dyn std::error::Error>),
+                        return Ok(match #call {
+                            Ok(value) => Ok(Box::new(value) as #ok_type),
+                            Err(value) => Err(<Box<dyn std::error::Error>>::from(value)),
                         });
                     }
                 }
                 else {
                     quote! {
-                        return Ok(self.#function_name(#(#function_parameters),*)#async_part.map(|value| Box::new(value) as Box<dyn std::any::Any>));
+                        return Ok(#call.map(|value| Box::new(value) as #ok_type));
                     }
                 }
             }
             else {
                 if err_needs_box {
                     quote! {
-                        return Ok(self.#function_name(#(#function_parameters),*)#async_part.map_err(|error| Box::new(error) as Box<dyn std::error::Error>));
+                        return Ok(#call.map_err(<Box<dyn std::error::Error>>::from));
                     }
                 }
                 else {
                     quote! {
-                        return Ok(self.#function_name(#(#function_parameters),*)#async_part);
+                        return Ok(#call);
                     }
                 }
             }
@@ -423,19 +947,134 @@ fn make_return_statement(function_definition: &FunctionDefintion, ok_needs_box:
         ReturnType::Other(_) => {
             if ok_needs_box {
                 quote! {
-                    return Ok(Ok(Box::new(self.#function_name(#(#function_parameters),*)#async_part)));
+                    return Ok(Ok(Box::new(#call) as #ok_type));
                 }
             }
             else {
                 quote! {
-                    return Ok(Ok(self.#function_name(#(#function_parameters),*)#async_part));
+                    return Ok(Ok(#call));
                 }
             }
         }
     }
 }
 
+/// `#[tool_part(flatten)]` is only possible for a function with a single parameter that is an object
+fn check_flatten(function_definition: &FunctionDefintion) -> syn::Result<()> {
+    let [parameter] = &*function_definition.parameters else {
+        return Err(syn::Error::new_spanned(
+            &function_definition.name,
+            format!("`flatten` requires the function `{}` to have exactly one parameter", function_definition.name),
+        ));
+    };
+    let param_type = match &parameter.param_type {
+        Type::Reference(type_reference) => &*type_reference.elem,
+        param_type => param_type,
+    };
+    if let Some(default) = &parameter.default {
+        return Err(syn::Error::new_spanned(
+            default,
+            format!("`flatten` parameter `{}` cannot have a default, since it is all of the parameters", parameter.name_str),
+        ));
+    }
+    if option_inner_type(param_type).is_some()
+        || rust_type_to_known_json_schema_type(param_type).is_some()
+        || rust_type_to_known_json_schema_item_type(param_type).is_some()
+    {
+        return Err(syn::Error::new_spanned(
+            &parameter.param_type,
+            format!("`flatten` requires the parameter `{}` to be a struct, since the parameters are always an object", parameter.name_str),
+        ));
+    }
+    Ok(())
+}
+
+/// Options passed to the parameter attribute e.g. `#[tool_param(rename = "...")]`
+#[derive(Default)]
+struct ToolParamAttributes {
+    /// The name the parameter is exposed as, instead of the Rust parameter name
+    rename: Option<String>,
+    /// The value used when the parameter is missing
+    default: Option<syn::Expr>,
+    /// Accept loosely typed values, only for `bool` parameters
+    coerce: bool,
+    /// Json schema keywords from `min`, `max`, `min_length`, `max_length` and `pattern`
+    constraints: Vec<TokenStream>,
+}
+
+impl ToolParamAttributes {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta, param_type: &Type) -> syn::Result<()> {
+        if meta.path.is_ident("rename") {
+            self.rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            return Ok(());
+        }
+        if meta.path.is_ident("default") {
+            self.default = Some(meta.value()?.parse::<syn::Expr>()?);
+            return Ok(());
+        }
+        if meta.path.is_ident("coerce") {
+            let is_bool = matches!(param_type, Type::Path(type_path) if type_path.path.is_ident("bool"));
+            if !is_bool {
+                return Err(meta.error("`coerce` is only supported for `bool` parameters"));
+            }
+            self.coerce = true;
+            return Ok(());
+        }
+        let mut json_schema_type = param_type;
+        while let Some(inner_type) = option_inner_type(json_schema_type) {
+            json_schema_type = inner_type;
+        }
+        let json_schema_type = rust_type_to_known_json_schema_type(json_schema_type);
+        let is_number = matches!(json_schema_type, Some("integer" | "number"));
+        let is_string = json_schema_type == Some("string");
+        let (keyword, value) = if meta.path.is_ident("min") || meta.path.is_ident("max") {
+            if !is_number {
+                return Err(meta.error("`min` and `max` are only supported for number parameters"));
+            }
+            let value = meta.value()?.parse::<syn::Expr>()?;
+            let keyword = if meta.path.is_ident("min") { "minimum" } else { "maximum" };
+            (keyword, value.to_token_stream())
+        } else if meta.path.is_ident("min_length") || meta.path.is_ident("max_length") {
+            if !is_string {
+                return Err(meta.error("`min_length` and `max_length` are only supported for string parameters"));
+            }
+            let value = meta.value()?.parse::<syn::LitInt>()?;
+            let keyword = if meta.path.is_ident("min_length") { "minLength" } else { "maxLength" };
+            (keyword, value.to_token_stream())
+        } else if meta.path.is_ident("pattern") {
+            if !is_string {
+                return Err(meta.error("`pattern` is only supported for string parameters"));
+            }
+            let value = meta.value()?.parse::<syn::LitStr>()?;
+            ("pattern", value.to_token_stream())
+        } else {
+            return Err(meta.error("unsupported tool_param attribute"));
+        };
+        self.constraints.push(quote! { #keyword: #value });
+        Ok(())
+    }
+
+    fn from_attrs(attrs: &[syn::Attribute], param_type: &Type) -> syn::Result<Self> {
+        let mut tool_param_attributes = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool_param")) {
+            attr.parse_nested_meta(|meta| tool_param_attributes.parse(meta, param_type))?;
+        }
+        Ok(tool_param_attributes)
+    }
+}
+
 fn extract_function_defintion(signature: Signature) -> syn::Result<FunctionDefintion> {
+    if let Some(receiver) = signature.receiver() {
+        if receiver.reference.is_some() && receiver.mutability.is_some() {
+            return Err(syn::Error::new_spanned(
+                receiver,
+                format!("`&mut self` cannot be used for the tool function `{}`, since tools are called \
+                through a shared reference. Take `&self` and keep the mutable state in a `RefCell`, \
+                `Mutex` or atomic instead.", signature.ident),
+            ));
+        }
+    }
+    let has_receiver = signature.receiver().is_some();
     let inputs = &signature.inputs;
     let parameters = inputs
         .iter()
@@ -446,56 +1085,135 @@ fn extract_function_defintion(signature: Signature) -> syn::Result<FunctionDefin
                     let name = pat_ident.ident.clone();
                     // let type_str = arg.ty.to_token_stream().to_string();
                     let type_ = *arg.ty.clone();
+                    let tool_param_attributes = match ToolParamAttributes::from_attrs(&arg.attrs, &type_) {
+                        Ok(tool_param_attributes) => tool_param_attributes,
+                        Err(error) => return Some(Err(error)),
+                    };
+                    let json_name = tool_param_attributes.rename.unwrap_or_else(|| name_str.clone());
 
-                    Some(Parameter {
+                    Some(Ok(Parameter {
                         name,
                         name_str,
+                        json_name,
                         param_type: type_,
                         description: None,
-                    })
+                        coerce: tool_param_attributes.coerce,
+                        constraints: tool_param_attributes.constraints,
+                        default: tool_param_attributes.default,
+                    }))
                 } else {
-                    None
+                    Some(Err(syn::Error::new_spanned(
+                        &arg.pat,
+                        "Tool parameters must be plain identifiers, since the identifier is the name of the \
+                        parameter in the schema. Destructure the parameter in the function body instead.",
+                    )))
                 }
             } else {
                 None
             }
         })
-        .collect::<Vec<_>>();
+        .collect::<syn::Result<Vec<_>>>()?;
 
-    let return_type = match signature.output {
-        syn::ReturnType::Default => {
+    for parameter in parameters.iter() {
+        if let Some(self_token) = find_self_token(parameter.param_type.to_token_stream()) {
             return Err(syn::Error::new_spanned(
-                signature,
-                "Currently, tool functions must have a return type, even if it is just `()`.",
-            ))
+                self_token,
+                format!("`Self` cannot be used in the type of the tool parameter `{}`, since the schema \
+                is generated for a concrete type. Use the concrete type instead.", parameter.name_str),
+            ));
         }
+        if let Some(wide_integer) = find_wide_integer_token(parameter.param_type.to_token_stream()) {
+            return Err(syn::Error::new_spanned(
+                wide_integer.clone(),
+                format!("`{wide_integer}` cannot be used for the tool parameter `{}`, since json numbers are \
+                only parsed as `i64`, `u64` or `f64`. Use a smaller integer type instead.", parameter.name_str),
+            ));
+        }
+        if let Type::Reference(type_reference) = &parameter.param_type {
+            if let Some(mutability) = type_reference.mutability {
+                return Err(syn::Error::new_spanned(
+                    mutability,
+                    format!("`&mut` cannot be used for the tool parameter `{}`, since parameters are \
+                    deserialized from the llm's call and any change would be discarded. Take the parameter \
+                    by value and return the changed value instead.", parameter.name_str),
+                ));
+            }
+        }
+    }
+
+    let return_type = match signature.output {
+        // e.g. a side effect such as sending an email
+        syn::ReturnType::Default => syn::parse_quote!(()),
         syn::ReturnType::Type(_, return_type) => *return_type,
     };
     let return_type = (|| {
         match &return_type {
             Type::Path(type_path) => {
                 let segments = &type_path.path.segments;
-                if segments.len() != 1 {
-                    return ReturnType::Other(OtherReturnType { other: return_type });
-                }
                 let segment = segments.last().unwrap();
-                if let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments {
-                    let mut generics = angle_bracketed_args.args.iter();
-
-                    if let (Some(GenericArgument::Type(okay)), Some(GenericArgument::Type(error))) =
-                        (generics.next(), generics.next())
-                    {
-                        return ReturnType::Result(ResultReturnType {
+                // other types with two generics e.g. `HashMap<K, V>` are not results. Any path
+                // ending in `Result` is, e.g. `std::result::Result<T, E>`
+                if segment.ident != "Result" {
+                    return Ok(ReturnType::Other(OtherReturnType { other: return_type }));
+                }
+                let path = segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>();
+                let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+                let generics = match &segment.arguments {
+                    PathArguments::AngleBracketed(angle_bracketed_args) => {
+                        angle_bracketed_args.args.iter().collect::<Vec<_>>()
+                    }
+                    _ => Vec::new(),
+                };
+                match generics.as_slice() {
+                    [GenericArgument::Type(okay), GenericArgument::Type(error)] => {
+                        return Ok(ReturnType::Result(ResultReturnType {
                             okay: okay.clone(),
                             error: error.clone(),
-                        });
+                        }));
+                    }
+                    // `std::fmt::Result` is `Result<(), std::fmt::Error>`. Other `Result`s without
+                    // generics may be the user's own type, so they are not treated as results
+                    [] if matches!(path.as_slice(), ["fmt", "Result"] | ["std", "fmt", "Result"]) => {
+                        return Ok(ReturnType::Result(ResultReturnType {
+                            okay: syn::parse_quote!(()),
+                            error: syn::parse_quote!(std::fmt::Error),
+                        }));
+                    }
+                    // The error of a single generic `Result` alias is only known for the common
+                    // aliases. Guessing e.g. `path::Error` is wrong for `std::thread::Result<T>`
+                    [GenericArgument::Type(okay)] => {
+                        let error: Type = match path.as_slice() {
+                            ["anyhow", "Result"] => syn::parse_quote!(anyhow::Error),
+                            ["io", "Result"] | ["std", "io", "Result"] => syn::parse_quote!(std::io::Error),
+                            _ => {
+                                return Err(syn::Error::new_spanned(
+                                    type_path,
+                                    "The error type of this `Result` alias is not known. Write the return \
+                                    type as `Result<T, E>` with the error type spelled out. Only the \
+                                    `anyhow::Result<T>` and `io::Result<T>` aliases are understood.",
+                                ));
+                            }
+                        };
+                        return Ok(ReturnType::Result(ResultReturnType {
+                            okay: okay.clone(),
+                            error,
+                        }));
                     }
+                    _ => {}
                 }
             }
             _ => {}
         }
-        return ReturnType::Other(OtherReturnType { other: return_type });
-    })();
+        Ok(ReturnType::Other(OtherReturnType { other: return_type }))
+    })()?;
+
+    // String like outputs are normalized to `String`, so there is a single type to downcast to
+    let mut return_type = return_type;
+    let string_like_okay = return_type.okay_type_mut();
+    let output_to_string = is_string_like(string_like_okay);
+    if output_to_string {
+        *string_like_okay = syn::parse_quote!(String);
+    }
 
     let is_async = signature.asyncness.is_some();
     let name = signature.ident;
@@ -507,9 +1225,121 @@ fn extract_function_defintion(signature: Signature) -> syn::Result<FunctionDefin
         parameters,
         return_type,
         description: None,
+        cfg_attrs: Vec::new(),
+        returns: None,
+        output_to_string,
+        has_receiver,
+        idempotent: false,
+        flatten: false,
+    })
+}
+
+/// The `T` of `Option<T>`, if `ty` is an option
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments else {
+        return None;
+    };
+    match angle_bracketed_args.args.first()? {
+        GenericArgument::Type(inner_type) => Some(inner_type),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is `Box<dyn Any>`, the type outputs are boxed into
+fn is_box_dyn_any(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    if segment.ident != "Box" {
+        return false;
+    }
+    let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments else {
+        return false;
+    };
+    let Some(GenericArgument::Type(Type::TraitObject(trait_object))) = angle_bracketed_args.args.first() else {
+        return false;
+    };
+    let mut bounds = trait_object.bounds.iter();
+    match (bounds.next(), bounds.next()) {
+        (Some(syn::TypeParamBound::Trait(bound)), None) => {
+            bound.path.segments.last().is_some_and(|segment| segment.ident == "Any")
+        }
+        _ => false,
+    }
+}
+
+/// Whether `ty` is `Arc<str>`, `Box<str>` or `Cow<str>`
+fn is_string_like(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    if !matches!(segment.ident.to_string().as_str(), "Arc" | "Box" | "Cow") {
+        return false;
+    }
+    let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments else {
+        return false;
+    };
+    angle_bracketed_args.args.iter().any(|arg| {
+        matches!(arg, GenericArgument::Type(Type::Path(type_path)) if type_path.path.is_ident("str"))
     })
 }
 
+/// Finds a `Self` token anywhere in `tokens`, e.g. `Self` or `Vec<Self>` or `<Self as Trait>::Type`
+fn find_self_token(tokens: TokenStream) -> Option<Ident> {
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "Self" => return Some(ident),
+            proc_macro2::TokenTree::Group(group) => {
+                if let Some(ident) = find_self_token(group.stream()) {
+                    return Some(ident);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The lines of the doc comments in `attrs`, with the attribute of each line
+fn doc_lines(attrs: &[syn::Attribute]) -> impl Iterator<Item = (&syn::Attribute, String)> {
+    attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(name_value) if name_value.path.is_ident("doc") => match &name_value.value {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(str), .. }) => Some((attr, str.value())),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Finds a `u128` or `i128` token anywhere in `tokens`, e.g. `u128` or `Option<i128>`
+fn find_wide_integer_token(tokens: TokenStream) -> Option<Ident> {
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "u128" || ident == "i128" => return Some(ident),
+            proc_macro2::TokenTree::Group(group) => {
+                if let Some(ident) = find_wide_integer_token(group.stream()) {
+                    return Some(ident);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 fn extract_description(
     function_definition: &mut FunctionDefintion,
     attrs: Vec<syn::Attribute>,
@@ -521,41 +1351,59 @@ fn extract_description(
         parameters,
         return_type: _,
         description,
+        cfg_attrs: _,
+        returns,
+        output_to_string: _,
+        has_receiver: _,
+        idempotent: _,
+        flatten: _,
     } = function_definition;
+    let returns_re = Regex::new(r"^\s*returns\s*-\s*(?<description>.*)$").unwrap();
     let re = Regex::new(r".*?`(?<name>.*?)`\s*-\s*(?<description>.*)$").unwrap();
-    for attr in attrs.iter() {
-        match &attr.meta {
-            syn::Meta::NameValue(name_value) => match &name_value.value {
-                syn::Expr::Lit(lit) => match &lit.lit {
-                    syn::Lit::Str(str) => {
-                        let haystack = str.value();
-                        let arg_caps = match re.captures(&haystack) {
-                            Some(caps) => caps,
-                            None => {
-                                if let Some(description) = description {
-                                    description.push_str(&*format!("{}\n", &str.value().trim()));
-                                } else {
-                                    let _ = description.insert(str.value().trim().to_string());
-                                }
-                                continue;
-                            }
-                        };
-                        let name = arg_caps["name"].to_string();
-                        let desc = arg_caps["description"].to_string();
-                        if let Some(param) = parameters.iter_mut().find(|p| p.name_str == name) {
-                            param.description = Some(desc);
-                        } else {
-                            return Err(syn::Error::new_spanned(
-                                attr,
-                                format!("parameter `{}` not found in function definition", name),
-                            ));
-                        }
-                    }
-                    _ => {}
-                },
-                _ => {}
-            },
-            _ => {}
+    let mut description_lines = Vec::new();
+    let mut in_code_block = false;
+    for (attr, haystack) in doc_lines(&attrs) {
+        // code blocks e.g. an example call are kept verbatim, even lines that look like a parameter
+        if haystack.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            description_lines.push(haystack.trim().to_string());
+            continue;
+        }
+        if in_code_block {
+            description_lines.push(haystack.strip_prefix(' ').unwrap_or(&haystack).trim_end().to_string());
+            continue;
+        }
+        if let Some(returns_caps) = returns_re.captures(&haystack) {
+            // `#[tool_part(returns = "...")]` takes precedence
+            if returns.is_none() {
+                let _ = returns.insert(returns_caps["description"].trim().to_string());
+            }
+            continue;
+        }
+        let arg_caps = match re.captures(&haystack) {
+            Some(caps) => caps,
+            None => {
+                description_lines.push(haystack.trim().to_string());
+                continue;
+            }
+        };
+        let name = arg_caps["name"].to_string();
+        let desc = arg_caps["description"].to_string();
+        if let Some(param) = parameters.iter_mut().find(|p| p.name_str == name) {
+            param.description = Some(desc);
+        } else {
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!("parameter `{}` not found in function definition", name),
+            ));
+        }
+    }
+    // `#[tool_part(description = "...")]` takes precedence
+    if description.is_none() {
+        let start = description_lines.iter().position(|line| !line.is_empty()).unwrap_or(description_lines.len());
+        let end = description_lines.iter().rposition(|line| !line.is_empty()).map_or(start, |end| end + 1);
+        if start < end {
+            let _ = description.insert(description_lines[start..end].join("\n"));
         }
     }
     for parameter in parameters {
@@ -585,8 +1433,7 @@ fn rust_type_to_known_json_schema_type(ty: &Type) -> Option<&'static str> {
                     "String" | "str" => Some("string"),
                     // json_serde only support `i64`, `u64`, `f64` as a final result
                     "i8" | "i16" | "i32" | "i64" | "isize" => Some("integer"),
-                    "u8" | "u16" | "u32" | "u64" | "usize" => Some("integer"), // todo if u, add to description it needs to b unsigned.
-                    "u128" | "i128" => Some("integer"), // todo compile_error!("json_serde only support `i64`, `u64`, `f64` as a final result. The the type needs to be compatible."),
+                    "u8" | "u16" | "u32" | "u64" | "usize" => Some("integer"), // range added by `unsigned_range_keywords`
                     "f32" | "f64" => Some("number"),
                     "bool" => Some("boolean"),
                     _ => None,
@@ -600,43 +1447,143 @@ fn rust_type_to_known_json_schema_type(ty: &Type) -> Option<&'static str> {
     }
 }
 
+/// `"minimum"` and `"maximum"` keywords, each followed by a comma, for the range of an unsigned
+/// integer type. The maximum is left out when it is too large to matter to an llm.
+fn unsigned_range_keywords(ty: &Type) -> TokenStream {
+    match ty {
+        Type::Path(type_path) => {
+            let Some(ident) = type_path.path.get_ident() else {
+                return quote! {};
+            };
+            match ident.to_string().as_str() {
+                "u8" => quote! { "minimum": 0, "maximum": u8::MAX, },
+                "u16" => quote! { "minimum": 0, "maximum": u16::MAX, },
+                "u32" => quote! { "minimum": 0, "maximum": u32::MAX, },
+                "u64" | "usize" => quote! { "minimum": 0, },
+                _ => quote! {},
+            }
+        }
+        Type::Reference(type_ref) => unsigned_range_keywords(&type_ref.elem),
+        _ => quote! {},
+    }
+}
+
+/// The value type `V` of a `HashMap<String, V>` or `BTreeMap<String, V>` where `V` has a known json
+/// schema type
+fn known_map_value_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(type_path) => {
+            let segment = type_path.path.segments.last()?;
+            if segment.ident != "HashMap" && segment.ident != "BTreeMap" {
+                return None;
+            }
+            let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments else {
+                return None;
+            };
+            let mut args = angle_bracketed_args.args.iter();
+            let (Some(GenericArgument::Type(key_type)), Some(GenericArgument::Type(value_type))) = (args.next(), args.next()) else {
+                return None;
+            };
+            if rust_type_to_known_json_schema_type(key_type) != Some("string") {
+                return None;
+            }
+            rust_type_to_known_json_schema_type(value_type).map(|_| value_type)
+        }
+        Type::Reference(type_ref) => known_map_value_type(&type_ref.elem),
+        _ => None,
+    }
+}
+
+/// `serde_json::Value`, a parameter that accepts any json. Only the `serde_json::Value` path is
+/// matched, a bare `Value` may be a type of the user with its own schema, so it is left to
+/// schemars.
+fn is_json_value(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => {
+            let segments = &type_path.path.segments;
+            type_path.qself.is_none()
+                && segments.len() == 2
+                && segments[0].ident == "serde_json"
+                && segments[1].ident == "Value"
+        }
+        Type::Reference(type_ref) => is_json_value(&type_ref.elem),
+        _ => false,
+    }
+}
+
+/// Attempt to determine the json schema type of the items at compile time, if the type is a `Vec`
+/// or slice of a type known by [rust_type_to_known_json_schema_type]
+fn rust_type_to_known_json_schema_item_type(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Path(type_path) => {
+            let segment = type_path.path.segments.last()?;
+            if segment.ident != "Vec" {
+                return None;
+            }
+            let PathArguments::AngleBracketed(angle_bracketed_args) = &segment.arguments else {
+                return None;
+            };
+            match angle_bracketed_args.args.first()? {
+                GenericArgument::Type(item_type) => rust_type_to_known_json_schema_type(item_type),
+                _ => None,
+            }
+        }
+        Type::Slice(type_slice) => rust_type_to_known_json_schema_type(&type_slice.elem),
+        Type::Reference(type_ref) => rust_type_to_known_json_schema_item_type(&type_ref.elem),
+        _ => None,
+    }
+}
+
 fn create_tool_json_schema(
     struct_name: &str,
     function_definitions: &Vec<FunctionDefintion>,
+    tool_attributes: &ToolAttributes,
 ) -> proc_macro2::TokenStream {
+    let group = tool_attributes.group.as_ref().map(|group| quote! {
+        "x-group": #group,
+    });
     let mut function_schemas = Vec::new();
     for function_definition in function_definitions {
         let id = function_definition.create_schema_const_indentifier(struct_name);
-        let description = &function_definition.description;
-        let name = &function_definition.name;
+        let description = function_definition.description.as_deref().unwrap_or_default();
+        let name_str = &function_definition.name_str;
+        let cfg_attrs = &function_definition.cfg_attrs;
+        let returns = function_definition.returns.as_ref().map(|returns| quote! {
+            "returns": #returns,
+        });
 
         function_schemas.push(quote! {
-            serde_json::json!(
+            #(#cfg_attrs)*
+            function_schemas.push(serde_json::json!(
                 {
                     "type": "object",
-                    "description": stringify!(#description),
+                    "description": #description,
+                    #returns
+                    #group
                     "properties": {
                         "function_name": {
-                            "const": stringify!(#name),
+                            "const": #name_str,
                         },
                         "parameters": *#id
                     },
                     "required": ["function_name", "parameters"]
                 }
-            )
+            ));
         });
     }
     let id = create_tool_schema_const_indentifier(struct_name);
     quote! {
-        const #id: std::cell::LazyCell<&'static serde_json::Value> = std::cell::LazyCell::new(|| {
-            Box::leak(Box::new(serde_json::json!(
+        static #id: std::sync::LazyLock<serde_json::Value> = std::sync::LazyLock::new(|| {
+            let mut function_schemas: Vec<serde_json::Value> = Vec::new();
+            #(#function_schemas)*
+            let mut schema = serde_json::json!(
                 {
                     "$schema": "http://json-schema.org/draft-07/schema#",
-                    "oneOf": [    
-                        #(#function_schemas),*
-                        ]
+                    "oneOf": function_schemas
                 }
-            )))
+            );
+            llmtoolbox::__private::hoist_definitions(&mut schema);
+            schema
         });
     }
 }
@@ -654,66 +1601,188 @@ fn create_function_parameter_json_schema(
     let mut computed_properties = Vec::new();
     let mut num_of_computed_properties = 0;
     for parameter in parameters {
-        let name = &parameter.name_str;
+        let name = &parameter.json_name;
         let description = &parameter.description;
         let param_type = &parameter.param_type;
-        let json_schema_type = rust_type_to_known_json_schema_type(&parameter.param_type);
-        if let Some(param_type) = json_schema_type {
+        // optional parameters are not required and may be `null`, however deeply the options are nested
+        let is_optional = option_inner_type(param_type).is_some();
+        let is_required = !is_optional && parameter.default.is_none();
+        let default = parameter.default.as_ref().map(|default| quote! {
+            "default": serde_json::json!(#default),
+        });
+        let mut required_type = param_type;
+        while let Some(inner_type) = option_inner_type(required_type) {
+            required_type = inner_type;
+        }
+        let nullable = |json_schema_type: &str| if is_optional {
+            quote! { [#json_schema_type, "null"] }
+        } else {
+            quote! { #json_schema_type }
+        };
+        let json_schema_type = rust_type_to_known_json_schema_type(required_type);
+        if is_json_value(required_type) {
+            // any json, so no `type`
             known_properties.push(quote! {
                 #name: {
-                    "type": #param_type,
+                    #default
                     "description": #description
                 }
             });
-            known_required_property_name.push(quote! {
-                #name
+            if is_required {
+                known_required_property_name.push(quote! {
+                    #name
+                });
+            }
+        } else if let Some(json_schema_type) = json_schema_type {
+            let json_schema_type = nullable(json_schema_type);
+            let constraints = &parameter.constraints;
+            let range = unsigned_range_keywords(required_type);
+            known_properties.push(quote! {
+                #name: {
+                    "type": #json_schema_type,
+                    #range
+                    #(#constraints,)*
+                    #default
+                    "description": #description
+                }
             });
+            if is_required {
+                known_required_property_name.push(quote! {
+                    #name
+                });
+            }
+        } else if let Some(value_type) = known_map_value_type(required_type) {
+            let json_schema_type = nullable("object");
+            let value_json_schema_type = rust_type_to_known_json_schema_type(value_type);
+            let range = unsigned_range_keywords(value_type);
+            known_properties.push(quote! {
+                #name: {
+                    "type": #json_schema_type,
+                    "additionalProperties": {
+                        #range
+                        "type": #value_json_schema_type
+                    },
+                    #default
+                    "description": #description
+                }
+            });
+            if is_required {
+                known_required_property_name.push(quote! {
+                    #name
+                });
+            }
+        } else if let Some(item_type) = rust_type_to_known_json_schema_item_type(required_type) {
+            let json_schema_type = nullable("array");
+            known_properties.push(quote! {
+                #name: {
+                    "type": #json_schema_type,
+                    "items": {
+                        "type": #item_type
+                    },
+                    #default
+                    "description": #description
+                }
+            });
+            if is_required {
+                known_required_property_name.push(quote! {
+                    #name
+                });
+            }
         } else {
             num_of_computed_properties +=1;
             let id = Ident::new(
                 &format!("computed{num_of_computed_properties}"),
                 json_schema_type.span(),
             );
+            let computed_default = parameter.default.as_ref().map(|default| quote! {
+                map.insert("default".to_string(), serde_json::json!(#default));
+            });
             computed_properties_outer_definitions.push(quote! {
                 let #id = (|| {
                     let schema_settings = schemars::generate::SchemaSettings::draft07();
                     let schema = schemars::SchemaGenerator::new(schema_settings).into_root_schema_for::<#param_type>();
                     let mut schema = schema.to_value();
                     llmtoolbox::clean_up_schema(&mut schema);
+                    llmtoolbox::__private::take_definitions(&mut schema, &mut definitions);
                     match schema {
                         serde_json::Value::Object(ref mut map) => { 
                             map.insert("description".to_string(), serde_json::Value::String(#description.to_string())); 
+                            #computed_default
                         },
                         _ => panic!("schema should always generate a map type.")
                     }
+                    llmtoolbox::__private::flatten_enum_constants(&mut schema);
+                    llmtoolbox::__private::describe_enum_variants(&mut schema);
                     return schema;
                 })();
             });
             computed_properties.push(quote! {
                 #name: #id
             });
-            computed_required_property_name.push(quote! {
-                #name
-            });
+            if is_required {
+                computed_required_property_name.push(quote! {
+                    #name
+                });
+            }
         }
     }
     let id = function_definition.create_schema_const_indentifier(struct_name);
+    let cfg_attrs = &function_definition.cfg_attrs;
+    if function_definition.flatten {
+        let parameter = &parameters[0];
+        let description = &parameter.description;
+        let param_type = match &parameter.param_type {
+            Type::Reference(type_reference) => &*type_reference.elem,
+            param_type => param_type,
+        };
+        return quote! {
+            #(#cfg_attrs)*
+            static #id: std::sync::LazyLock<serde_json::Value> = std::sync::LazyLock::new(|| {
+                let mut definitions = serde_json::Map::new();
+                let schema_settings = schemars::generate::SchemaSettings::draft07();
+                let schema = schemars::SchemaGenerator::new(schema_settings).into_root_schema_for::<#param_type>();
+                let mut schema = schema.to_value();
+                llmtoolbox::clean_up_schema(&mut schema);
+                llmtoolbox::__private::take_definitions(&mut schema, &mut definitions);
+                let map = schema.as_object_mut().expect("schema should always generate a map type.");
+                map.insert("description".to_string(), serde_json::Value::String(#description.to_string()));
+                if !definitions.is_empty() {
+                    for definition in definitions.values_mut() {
+                        llmtoolbox::__private::flatten_enum_constants(definition);
+                    }
+                    map.insert("definitions".to_string(), serde_json::Value::Object(definitions));
+                    llmtoolbox::__private::inline_refs(&mut schema);
+                }
+                schema
+            });
+        };
+    }
     quote! {
-        const #id: std::cell::LazyCell<serde_json::Value> = std::cell::LazyCell::new(|| {
+        #(#cfg_attrs)*
+        static #id: std::sync::LazyLock<serde_json::Value> = std::sync::LazyLock::new(|| {
+            let mut definitions = serde_json::Map::new();
             #(#computed_properties_outer_definitions)*
-            serde_json::json!(
+            let mut schema = serde_json::json!(
                 {
                     "type": "object",
                     "required": [
-                        #(#known_required_property_name),*
+                        #(#known_required_property_name,)*
                         #(#computed_required_property_name),*
                     ],
                     "properties": {
-                        #(#known_properties),*
+                        #(#known_properties,)*
                         #(#computed_properties),*
                     },
                 }
-            )
+            );
+            if !definitions.is_empty() {
+                for definition in definitions.values_mut() {
+                    llmtoolbox::__private::flatten_enum_constants(definition);
+                }
+                schema.as_object_mut().unwrap().insert("definitions".to_string(), serde_json::Value::Object(definitions));
+                llmtoolbox::__private::inline_refs(&mut schema);
+            }
+            schema
         });
     }
-}
+}
\ No newline at end of file
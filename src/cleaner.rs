@@ -0,0 +1,255 @@
+use serde_json::{Map, Value};
+
+/// Transforms a generated json schema, e.g. to suit what a provider accepts. The default removes
+/// the `$schema` and `title` keys, which is what [crate::clean_up_schema] does.
+#[derive(Debug, Clone)]
+pub struct SchemaCleaner {
+    remove_keys: Vec<String>,
+    additional_properties_false: bool,
+    inline_refs: bool,
+}
+
+impl Default for SchemaCleaner {
+    fn default() -> Self {
+        Self {
+            remove_keys: vec!["$schema".to_owned(), "title".to_owned()],
+            additional_properties_false: false,
+            inline_refs: false,
+        }
+    }
+}
+
+impl SchemaCleaner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The keys removed from every object in the schema, instead of `$schema` and `title`.
+    pub fn with_remove_keys(mut self, remove_keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.remove_keys = remove_keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds `"additionalProperties": false` to every object schema that does not set it, as
+    /// OpenAI strict mode requires.
+    pub fn with_additional_properties_false(mut self, additional_properties_false: bool) -> Self {
+        self.additional_properties_false = additional_properties_false;
+        self
+    }
+
+    /// Replaces each `$ref` with the definition it points to in the `definitions` (or `$defs`) of
+    /// the schema, for providers that do not resolve references. References to recursive types
+    /// are kept, along with their definitions, since they cannot be inlined. Those `$ref`s, e.g.
+    /// `#/definitions/Tree`, point at the root of the cleaned schema, so they only resolve while it
+    /// is the root of the document. Embed it elsewhere and the definitions must be copied to the
+    /// new root, as tool and toolbox schemas do for their parameter schemas.
+    pub fn with_inline_refs(mut self, inline_refs: bool) -> Self {
+        self.inline_refs = inline_refs;
+        self
+    }
+
+    pub fn clean(&self, schema: &mut Value) {
+        if self.inline_refs {
+            inline_refs(schema);
+        }
+        if !self.remove_keys.is_empty() {
+            for_each_object(schema, &mut |map| {
+                for key in &self.remove_keys {
+                    map.remove(key);
+                }
+            });
+        }
+        if self.additional_properties_false {
+            for_each_object(schema, &mut |map| {
+                let is_object_schema = map.contains_key("properties") || map.get("type").is_some_and(|type_| type_ == "object");
+                if is_object_schema && !map.contains_key("additionalProperties") {
+                    map.insert("additionalProperties".to_owned(), Value::Bool(false));
+                }
+            });
+        }
+    }
+}
+
+/// Calls `f` on every object in `value`, parents before children
+fn for_each_object(value: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    match value {
+        Value::Object(map) => {
+            f(map);
+            for (_, value) in map {
+                for_each_object(value, f);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                for_each_object(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn inline_refs(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    let blocks: Vec<(&str, Map<String, Value>)> = ["definitions", "$defs"]
+        .into_iter()
+        .filter_map(|key| match map.remove(key)? {
+            Value::Object(block) => Some((key, block)),
+            _ => None,
+        })
+        .collect();
+    let definitions: Map<String, Value> = blocks.iter().flat_map(|(_, block)| block.clone()).collect();
+    if definitions.is_empty() {
+        return;
+    }
+    // references to recursive types cannot be inlined, so these definitions are kept
+    let recursive: Vec<String> = definitions
+        .iter()
+        .filter(|(name, definition)| refers_to(definition, name, &definitions, &mut Vec::new()))
+        .map(|(name, _)| name.clone())
+        .collect();
+    inline_refs_with(schema, &definitions, &recursive);
+    let Value::Object(map) = schema else {
+        return;
+    };
+    for (key, mut block) in blocks {
+        block.retain(|name, _| recursive.contains(name));
+        for definition in block.values_mut() {
+            inline_refs_with(definition, &definitions, &recursive);
+        }
+        if !block.is_empty() {
+            map.insert(key.to_owned(), Value::Object(block));
+        }
+    }
+}
+
+/// The name of the definition `reference` points to e.g. `Tree` for `#/definitions/Tree`
+fn definition_name(reference: &str) -> &str {
+    reference
+        .trim_start_matches("#/definitions/")
+        .trim_start_matches("#/$defs/")
+}
+
+/// Whether `schema` refers to the definition `name`, directly or through other definitions
+fn refers_to(schema: &Value, name: &str, definitions: &Map<String, Value>, visited: &mut Vec<String>) -> bool {
+    match schema {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                let target = definition_name(reference);
+                if target == name {
+                    return true;
+                }
+                if !visited.iter().any(|visited| visited == target) {
+                    visited.push(target.to_owned());
+                    if definitions.get(target).is_some_and(|definition| refers_to(definition, name, definitions, visited)) {
+                        return true;
+                    }
+                }
+            }
+            map.values().any(|value| refers_to(value, name, definitions, visited))
+        }
+        Value::Array(values) => values.iter().any(|value| refers_to(value, name, definitions, visited)),
+        _ => false,
+    }
+}
+
+fn inline_refs_with(schema: &mut Value, definitions: &Map<String, Value>, recursive: &[String]) {
+    match schema {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                let name = definition_name(reference);
+                if recursive.iter().any(|recursive| recursive == name) {
+                    return;
+                }
+                let Some(definition) = definitions.get(name) else {
+                    return;
+                };
+                let mut inlined = definition.clone();
+                inline_refs_with(&mut inlined, definitions, recursive);
+                // keywords next to the `$ref`, e.g. `description`, take precedence
+                map.remove("$ref");
+                if let Value::Object(inlined) = inlined {
+                    for (key, value) in inlined {
+                        map.entry(key).or_insert(value);
+                    }
+                }
+                return;
+            }
+            for (_, value) in map {
+                inline_refs_with(value, definitions, recursive);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                inline_refs_with(value, definitions, recursive);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Copies the `definitions` (and `$defs`) of each function's parameter schema to the root of a
+/// tool or toolbox `schema`, where the `$ref`s kept for recursive types resolve from once the
+/// parameter schema is embedded under `oneOf`. The parameter schemas keep their own copy, so they
+/// still resolve on their own, e.g. as the `parameters` of a provider schema. A definition with
+/// the same name as a different definition of another function is renamed, along with its `$ref`s.
+pub(crate) fn hoist_definitions(schema: &mut Map<String, Value>) {
+    let mut hoisted: Vec<(&str, Map<String, Value>)> = Vec::new();
+    for key in ["definitions", "$defs"] {
+        schema.remove(key);
+        let mut block = Map::new();
+        if let Some(Value::Array(function_schemas)) = schema.get_mut("oneOf") {
+            for function_schema in function_schemas {
+                if let Some(Value::Object(parameters)) = function_schema.pointer_mut("/properties/parameters") {
+                    hoist_definitions_of(parameters, key, &mut block);
+                }
+            }
+        }
+        if !block.is_empty() {
+            hoisted.push((key, block));
+        }
+    }
+    for (key, block) in hoisted {
+        schema.insert(key.to_owned(), Value::Object(block));
+    }
+}
+
+/// Copies the `key` block of `parameters` into `hoisted`, renaming the definitions that clash
+fn hoist_definitions_of(parameters: &mut Map<String, Value>, key: &str, hoisted: &mut Map<String, Value>) {
+    let Some(Value::Object(definitions)) = parameters.get(key) else {
+        return;
+    };
+    let renames: Vec<(String, String)> = definitions
+        .iter()
+        .filter(|(name, definition)| hoisted.get(*name).is_some_and(|hoisted| hoisted != *definition))
+        .map(|(name, _)| {
+            let renamed = (2..)
+                .map(|index| format!("{name}{index}"))
+                .find(|renamed| !hoisted.contains_key(renamed) && !definitions.contains_key(renamed))
+                .expect("an unused definition name");
+            (name.clone(), renamed)
+        })
+        .collect();
+    for (name, renamed) in renames {
+        if let Some(Value::Object(definitions)) = parameters.get_mut(key) {
+            if let Some(definition) = definitions.remove(&name) {
+                definitions.insert(renamed.clone(), definition);
+            }
+        }
+        let reference = format!("#/{key}/{name}");
+        let renamed_reference = Value::String(format!("#/{key}/{renamed}"));
+        for value in parameters.values_mut() {
+            for_each_object(value, &mut |map| {
+                if map.get("$ref").is_some_and(|value| *value == *reference) {
+                    map.insert("$ref".to_owned(), renamed_reference.clone());
+                }
+            });
+        }
+    }
+    if let Some(Value::Object(definitions)) = parameters.get(key) {
+        for (name, definition) in definitions {
+            hoisted.entry(name.clone()).or_insert_with(|| definition.clone());
+        }
+    }
+}
@@ -3,9 +3,29 @@ error_set::error_set!{
     /// An error related to dynamically calling a function, not runing the function.
     /// Either there was an error parsing the arguments or the function did not exist.
     FunctionCallError = {
-        #[display("The function with name `{function_name}` was not found in the toolbox")]
+        #[display("The function with name `{}` was not found in the toolbox{}", function_name, did_you_mean.as_ref().map(|name| format!(", did you mean `{name}`?")).unwrap_or_default())]
         FunctionNotFound {
             function_name: String,
+            did_you_mean: Option<String>,
+        },
+        #[display("The output of the function `{function_name}` could not be serialized to json:\n{issue}")]
+        OutputSerialization {
+            function_name: String,
+            issue: String,
+        },
+        #[display("The output of the function `{function_name}` is not a `{expected}`")]
+        OutputTypeMismatch {
+            function_name: String,
+            expected: String,
+        },
+        #[display("The function `{function_name}` did not complete within {elapsed:?}")]
+        Timeout {
+            function_name: String,
+            elapsed: std::time::Duration,
+        },
+        #[display("The function `{function_name}` was cancelled")]
+        Cancelled {
+            function_name: String,
         },
     } || FunctionCallParsingError;
 
@@ -14,16 +34,160 @@ error_set::error_set!{
         #[display("An issue occured paring against the schema:\n{issue}")]
         Parsing {
             issue: String,
-        }
+        },
+        /// The parameters do not follow the schema of the function.
+        #[display("The parameter at `{path}` does not follow the schema:\n{expected}")]
+        SchemaViolation {
+            path: String,
+            expected: String,
+        },
+        /// The call has parameters the function does not take.
+        #[display("The function `{function_name}` does not take the parameters {parameter_names:?}")]
+        UnknownParameters {
+            function_name: String,
+            parameter_names: Vec<String>,
+        },
+        /// The call has no function name, in the field `field` e.g. `function_name`.
+        #[display("The tool call is missing the `{field}` field in:\n{call}")]
+        MissingFunctionName {
+            field: String,
+            call: String,
+        },
+        /// The function name of the call, in the field `field`, is not a string.
+        #[display("The tool call `{field}` field is not a string in:\n{call}")]
+        FunctionNameNotString {
+            field: String,
+            call: String,
+        },
+        /// The call has no parameters, in the field `field` e.g. `parameters`.
+        #[display("The tool call is missing the `{field}` field in:\n{call}")]
+        MissingParameters {
+            field: String,
+            call: String,
+        },
+        /// The parameters of the call, in the field `field`, are not an object.
+        #[display("The tool call `{field}` field is not an object in:\n{call}")]
+        ParametersNotObject {
+            field: String,
+            call: String,
+        },
     };
 }
 
 impl FunctionCallError {
     pub fn function_not_found(function_name: String) -> Self {
-        Self::FunctionNotFound { function_name }
+        Self::FunctionNotFound { function_name, did_you_mean: None }
+    }
+
+    pub fn output_serialization(function_name: String, issue: String) -> Self {
+        Self::OutputSerialization { function_name, issue }
     }
 
     pub fn parsing(issue: String) -> Self {
         Self::Parsing { issue }
     }
-}
\ No newline at end of file
+
+    /// What went wrong, without the details, e.g. to decide whether a call is worth retrying.
+    pub fn kind(&self) -> FunctionCallErrorKind {
+        match self {
+            Self::FunctionNotFound { .. } => FunctionCallErrorKind::FunctionNotFound,
+            Self::OutputSerialization { .. } => FunctionCallErrorKind::OutputSerialization,
+            Self::OutputTypeMismatch { .. } => FunctionCallErrorKind::OutputTypeMismatch,
+            Self::Timeout { .. } => FunctionCallErrorKind::Timeout,
+            Self::Cancelled { .. } => FunctionCallErrorKind::Cancelled,
+            Self::Parsing { .. } => FunctionCallErrorKind::Parsing,
+            Self::SchemaViolation { .. } => FunctionCallErrorKind::SchemaViolation,
+            Self::UnknownParameters { .. } => FunctionCallErrorKind::UnknownParameters,
+            Self::MissingFunctionName { .. } => FunctionCallErrorKind::MissingFunctionName,
+            Self::FunctionNameNotString { .. } => FunctionCallErrorKind::FunctionNameNotString,
+            Self::MissingParameters { .. } => FunctionCallErrorKind::MissingParameters,
+            Self::ParametersNotObject { .. } => FunctionCallErrorKind::ParametersNotObject,
+        }
+    }
+}
+
+impl FunctionCallParsingError {
+    /// What went wrong, without the details, see [FunctionCallError::kind].
+    pub fn kind(&self) -> FunctionCallErrorKind {
+        match self {
+            Self::Parsing { .. } => FunctionCallErrorKind::Parsing,
+            Self::SchemaViolation { .. } => FunctionCallErrorKind::SchemaViolation,
+            Self::UnknownParameters { .. } => FunctionCallErrorKind::UnknownParameters,
+            Self::MissingFunctionName { .. } => FunctionCallErrorKind::MissingFunctionName,
+            Self::FunctionNameNotString { .. } => FunctionCallErrorKind::FunctionNameNotString,
+            Self::MissingParameters { .. } => FunctionCallErrorKind::MissingParameters,
+            Self::ParametersNotObject { .. } => FunctionCallErrorKind::ParametersNotObject,
+        }
+    }
+}
+
+/// The kind of a [FunctionCallError]. New kinds may be added, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FunctionCallErrorKind {
+    FunctionNotFound,
+    OutputSerialization,
+    OutputTypeMismatch,
+    Timeout,
+    Cancelled,
+    Parsing,
+    SchemaViolation,
+    UnknownParameters,
+    MissingFunctionName,
+    FunctionNameNotString,
+    MissingParameters,
+    ParametersNotObject,
+}
+
+impl FunctionCallErrorKind {
+    /// Whether the llm may succeed by making the call again with different arguments, i.e. the
+    /// call itself was malformed. Errors of the function or toolbox, e.g. a missing function, are
+    /// not.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Parsing
+                | Self::SchemaViolation
+                | Self::UnknownParameters
+                | Self::MissingFunctionName
+                | Self::FunctionNameNotString
+                | Self::MissingParameters
+                | Self::ParametersNotObject
+        )
+    }
+}
+
+impl From<serde_json::Error> for FunctionCallParsingError {
+    fn from(error: serde_json::Error) -> Self {
+        let issue = if error.is_eof() {
+            format!("The tool call json ends early, it may be truncated: {error}")
+        } else {
+            format!("The tool call is not valid json: {error}")
+        };
+        Self::Parsing { issue }
+    }
+}
+
+/// A tool could not be added to a toolbox, since a function with the same name already exists.
+pub struct AddToolError<T> {
+    /// The tool that was not added
+    pub tool: T,
+    /// The function name that exists in both
+    pub conflicting_name: String,
+}
+
+impl<T> std::fmt::Debug for AddToolError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AddToolError")
+            .field("conflicting_name", &self.conflicting_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> std::fmt::Display for AddToolError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A function with the name `{}` already exists in the toolbox", self.conflicting_name)
+    }
+}
+
+impl<T> std::error::Error for AddToolError<T> {}
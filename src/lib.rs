@@ -1,34 +1,55 @@
+mod cleaner;
 mod errors;
+mod multi;
+mod partial;
+mod private;
+mod providers;
+mod resolved;
+#[cfg(feature = "stats")]
+mod stats;
 mod tool;
 mod toolbox;
 mod utils;
+mod view;
+#[cfg(feature = "validation")]
+mod validation;
 
 pub use tool::*;
+pub use cleaner::SchemaCleaner;
 pub use toolbox::*;
 pub use llmtool::*;
 pub use errors::*;
+pub use multi::*;
+pub use partial::PartialFunctionCall;
+pub use resolved::*;
+pub use view::*;
+#[cfg(feature = "stats")]
+pub use stats::FunctionStats;
+#[cfg(feature = "validation")]
+pub use validation::assert_valid_schema;
 
+/// Used by the code `#[tool]` generates, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::private::*;
+}
+
+/// Removes keys from a generated schema that are not useful to an llm. Only the json tree is
+/// walked, `$ref`s are never followed, so schemas of recursive types are fine. See
+/// [SchemaCleaner] to configure this.
 pub fn clean_up_schema(schema: &mut serde_json::Value) {
-    match schema {
-        serde_json::Value::Object(map) => {
-            map.remove("$schema");
-            map.remove("title");
-            for (_, value) in map {
-                clean_up_schema_rest(value);
-            }
-        },
-        _ => {}
-    }
+    SchemaCleaner::default().clean(schema);
 }
 
 pub fn clean_up_schema_rest(schema: &mut serde_json::Value) {
-    match schema {
-        serde_json::Value::Object(map) => {
-            map.remove("title");
-            for (_, value) in map {
-                clean_up_schema_rest(value);
-            }
-        },
-        _ => {}
-    }
-}
\ No newline at end of file
+    SchemaCleaner::default().with_remove_keys(["title"]).clean(schema);
+}
+
+/// Writes `schema` to `path` as pretty-printed json, with the keys of every object sorted so the
+/// file diffs cleanly when checked in, e.g. from a build script.
+pub fn write_schema(schema: &serde_json::Map<String, serde_json::Value>, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+    let schema = providers::sort_keys(serde_json::Value::Object(schema.clone()));
+    let mut contents = serde_json::to_string_pretty(&schema)?;
+    contents.push('\n');
+    std::fs::write(path, contents)
+}
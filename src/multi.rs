@@ -0,0 +1,172 @@
+use futures_util::future::join_all;
+use serde_json::{Map, Value};
+
+use crate::{parse_function_call, providers, FunctionCallError, Tool};
+
+/// A collection of tools where several tools may have a function with the same name, e.g. plugins
+/// that each react to the same event. [MultiToolBoxLocal::call_all] calls every tool with the
+/// function. Unlike [crate::ToolBoxLocal], adding a tool never fails. [Tool] does not need to be
+/// Send or Sync, see [MultiToolBox] if needed.
+pub struct MultiToolBoxLocal<O, E> {
+    /// all the tools that the llm can call
+    all_tools: Vec<Box<dyn Tool<O, E>>>,
+    /// schema to be sent to the llm, with the first schema of each function name
+    schema: Map<String, Value>,
+}
+
+impl<O, E> Default for MultiToolBoxLocal<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, E> MultiToolBoxLocal<O, E> {
+    pub fn new() -> Self {
+        Self {
+            all_tools: Vec::new(),
+            schema: Map::new(),
+        }
+    }
+
+    /// Adds the `tool` to this toolbox, even if another tool has a function with the same name.
+    pub fn add_tool<T: Tool<O, E> + 'static>(&mut self, tool: T) {
+        self.add_boxed_tool(Box::new(tool));
+    }
+
+    /// Adds the boxed `tool` to this toolbox, even if another tool has a function with the same
+    /// name.
+    pub fn add_boxed_tool(&mut self, tool: Box<dyn Tool<O, E>>) {
+        extend_schema_once(&mut self.schema, tool.schema());
+        self.all_tools.push(tool);
+    }
+
+    /// Calls every tool with the function of the given name, concurrently, with the same
+    /// parameters. The results are in the order the tools were added. An error is returned instead
+    /// if the call cannot be parsed, no tool has the function or a tool could not be called e.g.
+    /// the parameters do not match its function.
+    pub async fn call_all(&self, function_call: Value) -> Result<Vec<Result<O, E>>, FunctionCallError> {
+        call_all(&self.all_tools, function_call).await
+    }
+
+    /// The number of tools in this toolbox
+    pub fn len(&self) -> usize {
+        self.all_tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all_tools.is_empty()
+    }
+
+    /// The schema of every function name, taken from the first tool added with the function.
+    pub fn schema(&self) -> &Map<String, Value> {
+        &self.schema
+    }
+}
+
+/// A collection of tools where several tools may have a function with the same name, e.g. plugins
+/// that each react to the same event. [MultiToolBox::call_all] calls every tool with the function.
+/// Unlike [crate::ToolBox], adding a tool never fails.
+pub struct MultiToolBox<O, E> {
+    /// all the tools that the llm can call
+    all_tools: Vec<Box<dyn Tool<O, E> + Send + Sync>>,
+    /// schema to be sent to the llm, with the first schema of each function name
+    schema: Map<String, Value>,
+}
+
+impl<O, E> Default for MultiToolBox<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, E> MultiToolBox<O, E> {
+    pub fn new() -> Self {
+        Self {
+            all_tools: Vec::new(),
+            schema: Map::new(),
+        }
+    }
+
+    /// Adds the `tool` to this toolbox, even if another tool has a function with the same name.
+    pub fn add_tool<T: Tool<O, E> + Send + Sync + 'static>(&mut self, tool: T) {
+        self.add_boxed_tool(Box::new(tool));
+    }
+
+    /// Adds the boxed `tool` to this toolbox, even if another tool has a function with the same
+    /// name.
+    pub fn add_boxed_tool(&mut self, tool: Box<dyn Tool<O, E> + Send + Sync>) {
+        extend_schema_once(&mut self.schema, tool.schema());
+        self.all_tools.push(tool);
+    }
+
+    /// Calls every tool with the function of the given name, concurrently, with the same
+    /// parameters. The results are in the order the tools were added. An error is returned instead
+    /// if the call cannot be parsed, no tool has the function or a tool could not be called e.g.
+    /// the parameters do not match its function.
+    pub async fn call_all(&self, function_call: Value) -> Result<Vec<Result<O, E>>, FunctionCallError> {
+        call_all(&self.all_tools, function_call).await
+    }
+
+    /// The number of tools in this toolbox
+    pub fn len(&self) -> usize {
+        self.all_tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all_tools.is_empty()
+    }
+
+    /// The schema of every function name, taken from the first tool added with the function.
+    pub fn schema(&self) -> &Map<String, Value> {
+        &self.schema
+    }
+}
+
+/// Calls every tool in `all_tools` that has the function of `function_call`, see
+/// [MultiToolBoxLocal::call_all]
+async fn call_all<O, E, T: Tool<O, E> + ?Sized>(
+    all_tools: &[Box<T>],
+    function_call: Value,
+) -> Result<Vec<Result<O, E>>, FunctionCallError> {
+    let function_call = parse_function_call(function_call)?;
+    let tools: Vec<_> = all_tools
+        .iter()
+        .filter(|tool| tool.function_names().contains(&&*function_call.function_name))
+        .collect();
+    if tools.is_empty() {
+        return Err(FunctionCallError::function_not_found(function_call.function_name));
+    }
+    join_all(tools.into_iter().map(|tool| {
+        tool.call_function(&function_call.function_name, function_call.parameters.clone())
+    }))
+    .await
+    .into_iter()
+    .collect()
+}
+
+/// Adds the `oneOf` entries of `tool_schema` to `schema`, skipping functions already in `schema`,
+/// and hoists their definitions
+fn extend_schema_once(schema: &mut Map<String, Value>, tool_schema: &Map<String, Value>) {
+    for (key, value) in tool_schema {
+        if key != "oneOf" {
+            schema.insert(key.clone(), value.clone());
+            continue;
+        }
+        let functions = schema
+            .entry("oneOf")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        let Value::Array(functions) = functions else {
+            continue;
+        };
+        for function_schema in providers::function_schemas(tool_schema) {
+            let function_name = &function_schema["properties"]["function_name"]["const"];
+            let exists = functions
+                .iter()
+                .any(|function| &function["properties"]["function_name"]["const"] == function_name);
+            if !exists {
+                functions.push(function_schema.clone());
+            }
+        }
+    }
+    crate::cleaner::hoist_definitions(schema);
+}
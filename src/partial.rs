@@ -0,0 +1,108 @@
+use serde_json::{Map, Value};
+
+use crate::{FunctionCallArgs, FunctionCallParsingError};
+
+/// The result of parsing arguments that may still be streaming in, see
+/// [FunctionCallArgs::try_from_partial].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartialFunctionCall {
+    /// All the arguments have arrived.
+    Complete(FunctionCallArgs),
+    /// More data is needed. Holds the arguments that could be recovered so far, the last of which
+    /// may still be truncated e.g. a string that is only partially received.
+    Incomplete(FunctionCallArgs),
+}
+
+impl FunctionCallArgs {
+    /// Parses the arguments of a call to `function_name` from json that a provider may still be
+    /// streaming, e.g. `{"city": "Par`. Truncated json is closed on a best effort basis, so the
+    /// call can be shown before all the arguments have arrived.
+    pub fn try_from_partial(
+        function_name: &str,
+        partial_json: &str,
+    ) -> Result<PartialFunctionCall, FunctionCallParsingError> {
+        let into_args = |parameters: Map<String, Value>| FunctionCallArgs {
+            function_name: function_name.to_owned(),
+            parameters,
+            id: None,
+        };
+        match serde_json::from_str::<Value>(partial_json) {
+            Ok(Value::Object(parameters)) => {
+                return Ok(PartialFunctionCall::Complete(into_args(parameters)))
+            }
+            Ok(_) => {
+                return Err(FunctionCallParsingError::Parsing {
+                    issue: format!("The arguments of `{function_name}` are not an object"),
+                })
+            }
+            Err(error) if !error.is_eof() => {
+                return Err(FunctionCallParsingError::Parsing {
+                    issue: format!("The arguments of `{function_name}` are not valid json:\n{error}"),
+                })
+            }
+            Err(_) => {}
+        }
+        let parameters = close_partial_json(partial_json)
+            .and_then(|value| match value {
+                Value::Object(parameters) => Some(parameters),
+                _ => None,
+            })
+            .unwrap_or_default();
+        Ok(PartialFunctionCall::Incomplete(into_args(parameters)))
+    }
+}
+
+/// Closes the open strings, arrays and objects of truncated json. If the json ends in the middle
+/// of a key or literal, it is cut back to the last complete element.
+fn close_partial_json(partial_json: &str) -> Option<Value> {
+    // the open brackets, and the places the json can be cut with the brackets open at that point
+    let mut open = Vec::new();
+    let mut cut_points: Vec<(usize, Vec<char>)> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, char) in partial_json.char_indices() {
+        if in_string {
+            match char {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match char {
+            '"' => in_string = true,
+            '{' | '[' => {
+                open.push(char);
+                cut_points.push((index + 1, open.clone()));
+            }
+            '}' | ']' => {
+                open.pop();
+            }
+            ',' => cut_points.push((index, open.clone())),
+            _ => {}
+        }
+    }
+    let mut text = partial_json.to_owned();
+    if in_string {
+        if escaped {
+            text.pop();
+        }
+        text.push('"');
+    }
+    let candidates = std::iter::once((text, open)).chain(
+        cut_points
+            .into_iter()
+            .rev()
+            .map(|(index, open)| (partial_json[..index].to_owned(), open)),
+    );
+    for (mut text, open) in candidates {
+        for bracket in open.iter().rev() {
+            text.push(if *bracket == '{' { '}' } else { ']' });
+        }
+        if let Ok(value) = serde_json::from_str(&text) {
+            return Some(value);
+        }
+    }
+    None
+}
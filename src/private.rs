@@ -0,0 +1,154 @@
+/// Moves the `definitions` (and `$defs`) of `schema` into `definitions`. Used to hoist the
+/// definitions of a parameter's schema to the root of the function's parameter schema, so that
+/// `$ref`s like `#/definitions/TreeNode` still resolve, which is required for recursive types.
+pub fn take_definitions(schema: &mut serde_json::Value, definitions: &mut serde_json::Map<String, serde_json::Value>) {
+    if let serde_json::Value::Object(map) = schema {
+        for key in ["definitions", "$defs"] {
+            if let Some(serde_json::Value::Object(schema_definitions)) = map.remove(key) {
+                definitions.extend(schema_definitions);
+            }
+        }
+    }
+}
+
+/// Replaces each `$ref` in `schema` with the definition it points to, since many providers do not
+/// resolve references. The `definitions` are removed, unless a type is recursive, in which case
+/// its references are kept since they cannot be inlined. They resolve against the root of
+/// `schema`, see [hoist_definitions] for when it is embedded in a tool schema.
+pub fn inline_refs(schema: &mut serde_json::Value) {
+    crate::cleaner::inline_refs(schema);
+}
+
+/// Copies the definitions of each function's parameter schema to the root of a tool `schema`, so
+/// the `$ref`s of recursive types resolve in the combined schema too.
+pub fn hoist_definitions(schema: &mut serde_json::Value) {
+    if let serde_json::Value::Object(schema) = schema {
+        crate::cleaner::hoist_definitions(schema);
+    }
+}
+
+/// For a schema of a fieldless enum that is a `oneOf` of string constants, which schemars emits
+/// when the variants have doc comments, replaces the `oneOf` with a flat
+/// `{"type": "string", "enum": [..]}`. The variant descriptions are appended to the `description`.
+pub fn flatten_enum_constants(schema: &mut serde_json::Value) {
+    let Some(variants) = schema.get("oneOf").and_then(serde_json::Value::as_array) else {
+        return;
+    };
+    let mut values = Vec::new();
+    let mut variant_descriptions = Vec::new();
+    for variant in variants {
+        let Some(variant) = variant.as_object() else {
+            return;
+        };
+        let is_constant = variant
+            .keys()
+            .all(|key| matches!(key.as_str(), "type" | "const" | "enum" | "description"));
+        if !is_constant || variant.get("type").is_some_and(|type_| type_ != "string") {
+            return;
+        }
+        let variant_values = match (variant.get("const"), variant.get("enum")) {
+            (Some(serde_json::Value::String(value)), None) => vec![value.as_str()],
+            (None, Some(serde_json::Value::Array(enum_values))) => {
+                match enum_values.iter().map(serde_json::Value::as_str).collect::<Option<Vec<_>>>() {
+                    Some(enum_values) => enum_values,
+                    None => return,
+                }
+            }
+            _ => return,
+        };
+        if let Some(description) = variant.get("description").and_then(serde_json::Value::as_str) {
+            for value in &variant_values {
+                variant_descriptions.push(format!("`{value}` - {description}"));
+            }
+        }
+        values.extend(variant_values.into_iter().map(|value| serde_json::Value::String(value.to_owned())));
+    }
+    let serde_json::Value::Object(map) = schema else {
+        return;
+    };
+    map.remove("oneOf");
+    map.insert("type".to_owned(), serde_json::Value::String("string".to_owned()));
+    map.insert("enum".to_owned(), serde_json::Value::Array(values));
+    if !variant_descriptions.is_empty() {
+        let variant_descriptions = variant_descriptions.join("\n");
+        let description = match map.get("description").and_then(serde_json::Value::as_str) {
+            Some(description) => format!("{description}\n{variant_descriptions}"),
+            None => variant_descriptions,
+        };
+        map.insert("description".to_owned(), serde_json::Value::String(description));
+    }
+}
+
+/// For a schema of an enum with data (a `oneOf` of variants), appends the variant names to the
+/// `description`, so the llm knows the discriminants it can pick from. Llms follow the adjacently
+/// tagged `{"type": "ById", "value": 1}` representation, i.e.
+/// `#[serde(tag = "type", content = "value")]`, more reliably than the default externally tagged
+/// `{"ById": 1}` representation.
+pub fn describe_enum_variants(schema: &mut serde_json::Value) {
+    let Some(variants) = schema.get("oneOf").and_then(serde_json::Value::as_array) else {
+        return;
+    };
+    let mut discriminants = Vec::new();
+    for variant in variants {
+        let variant_discriminants = enum_variant_discriminants(variant);
+        if variant_discriminants.is_empty() {
+            return;
+        }
+        discriminants.extend(variant_discriminants);
+    }
+    let variants = discriminants
+        .iter()
+        .map(|discriminant| format!("`{discriminant}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let serde_json::Value::Object(map) = schema else {
+        return;
+    };
+    let description = match map.get("description").and_then(serde_json::Value::as_str) {
+        Some(description) => format!("{description}\nThe variants are: {variants}"),
+        None => format!("The variants are: {variants}"),
+    };
+    map.insert("description".to_owned(), serde_json::Value::String(description));
+}
+
+/// The discriminants of a single `oneOf` entry of an enum schema, for the unit, externally tagged
+/// and internally/adjacently tagged representations.
+fn enum_variant_discriminants(variant: &serde_json::Value) -> Vec<&str> {
+    if let Some(values) = variant.get("enum").and_then(serde_json::Value::as_array) {
+        return values.iter().filter_map(serde_json::Value::as_str).collect();
+    }
+    if let Some(value) = variant.get("const").and_then(serde_json::Value::as_str) {
+        return vec![value];
+    }
+    let Some(properties) = variant.get("properties").and_then(serde_json::Value::as_object) else {
+        return Vec::new();
+    };
+    let tag = properties
+        .values()
+        .find_map(|property| property.get("const").and_then(serde_json::Value::as_str));
+    if let Some(tag) = tag {
+        return vec![tag];
+    }
+    let is_externally_tagged = properties.len() == 1
+        && variant.get("additionalProperties") == Some(&serde_json::Value::Bool(false));
+    if is_externally_tagged {
+        return properties.keys().map(String::as_str).collect();
+    }
+    Vec::new()
+}
+
+/// Converts a loosely typed json value to a `bool`. Accepts booleans, `"true"`/`"false"` in any case
+/// and `0`/`1`.
+pub fn coerce_bool(value: &serde_json::Value) -> Option<bool> {
+    match value {
+        serde_json::Value::Bool(value) => Some(*value),
+        serde_json::Value::String(value) if value.eq_ignore_ascii_case("true") => Some(true),
+        serde_json::Value::String(value) if value.eq_ignore_ascii_case("false") => Some(false),
+        serde_json::Value::Number(value) => match value.as_u64() {
+            Some(0) => Some(false),
+            Some(1) => Some(true),
+            _ => None,
+        },
+        _ => None,
+    }
+}
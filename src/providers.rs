@@ -0,0 +1,333 @@
+use serde_json::{json, Map, Value};
+
+/// The `oneOf` entries of a tool's schema, one for each function of the tool
+pub(crate) fn function_schemas(tool_schema: &Map<String, Value>) -> impl Iterator<Item = &Value> {
+    tool_schema
+        .get("oneOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// The parameter schema of a `oneOf` entry. Always an object schema with `properties`, even if
+/// the function takes no parameters. It keeps its own `definitions` for the `$ref`s of recursive
+/// types, which resolve since it is the root of the provider's parameter schema.
+pub(crate) fn parameters_schema(function_schema: &Value) -> Value {
+    let mut parameters = match &function_schema["properties"]["parameters"] {
+        Value::Object(parameters) => parameters.clone(),
+        _ => Map::new(),
+    };
+    parameters
+        .entry("type")
+        .or_insert_with(|| Value::String("object".to_owned()));
+    parameters
+        .entry("properties")
+        .or_insert_with(|| Value::Object(Map::new()));
+    Value::Object(parameters)
+}
+
+/// A copy of the `oneOf` entry with `prefix` and `suffix` put on their own lines around the
+/// description. Empty parts are skipped.
+pub(crate) fn wrap_description(function_schema: &Value, prefix: &str, suffix: &str) -> Value {
+    let mut function_schema = function_schema.clone();
+    if prefix.is_empty() && suffix.is_empty() {
+        return function_schema;
+    }
+    if let Value::Object(map) = &mut function_schema {
+        let description = map.get("description").and_then(Value::as_str).unwrap_or_default();
+        let description = [prefix, description, suffix]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        map.insert("description".to_owned(), Value::String(description));
+    }
+    function_schema
+}
+
+/// Converts a `oneOf` entry into an OpenAI Chat Completions tool definition
+pub(crate) fn openai_function_schema(function_schema: &Value) -> Value {
+    let mut function = Map::new();
+    function.insert(
+        "name".to_owned(),
+        function_schema["properties"]["function_name"]["const"].clone(),
+    );
+    if let Some(description) = function_schema.get("description") {
+        function.insert("description".to_owned(), description.clone());
+    }
+    function.insert("parameters".to_owned(), parameters_schema(function_schema));
+    json!({
+        "type": "function",
+        "function": function
+    })
+}
+
+/// Converts a `oneOf` entry into an OpenAI Chat Completions tool definition for strict mode, see
+/// [strict_schema]
+pub(crate) fn strict_openai_function_schema(function_schema: &Value) -> Value {
+    let mut tool = openai_function_schema(function_schema);
+    let function = &mut tool["function"];
+    strict_schema(&mut function["parameters"]);
+    function["strict"] = Value::Bool(true);
+    tool
+}
+
+/// Tightens a json schema for OpenAI strict mode. Every object gets `"additionalProperties": false`
+/// and all of its properties are required. Since a property can then not be omitted, properties
+/// that were optional are made nullable instead, e.g. `"type": ["integer", "null"]`, and `null`
+/// deserializes the same as a missing value for `Option`s. `oneOf` becomes `anyOf`, which strict
+/// mode supports. An object that already sets `additionalProperties`, e.g. the schema of a
+/// `HashMap<String, u32>`, is left open, since forcing `false` would only accept `{}`. Strict mode
+/// does not support such free-form maps, so the provider may reject a schema with them.
+fn strict_schema(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    if let Some(one_of) = map.remove("oneOf") {
+        map.insert("anyOf".to_owned(), one_of);
+    }
+    let required: Vec<Value> = match map.get("required") {
+        Some(Value::Array(required)) => required.clone(),
+        _ => Vec::new(),
+    };
+    if let Some(Value::Object(properties)) = map.get_mut("properties") {
+        for (name, property) in properties.iter_mut() {
+            if !required.iter().any(|required| required == name) {
+                make_nullable(property);
+            }
+        }
+        let all_properties = properties.keys().cloned().map(Value::String).collect();
+        map.insert("required".to_owned(), Value::Array(all_properties));
+        map.entry("additionalProperties").or_insert(Value::Bool(false));
+    } else if map.get("type").is_some_and(|type_| type_ == "object") && !map.contains_key("additionalProperties") {
+        map.insert("properties".to_owned(), Value::Object(Map::new()));
+        map.insert("required".to_owned(), Value::Array(Vec::new()));
+        map.insert("additionalProperties".to_owned(), Value::Bool(false));
+    }
+    for (key, value) in map.iter_mut() {
+        match (key.as_str(), value) {
+            ("properties" | "definitions" | "$defs", Value::Object(schemas)) => {
+                schemas.values_mut().for_each(strict_schema);
+            }
+            ("anyOf" | "allOf", Value::Array(schemas)) => schemas.iter_mut().for_each(strict_schema),
+            ("items" | "additionalProperties", items) => strict_schema(items),
+            _ => {}
+        }
+    }
+}
+
+/// Allows `null` for `schema`, if it does not already
+fn make_nullable(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    let null = Value::String("null".to_owned());
+    match map.get_mut("type") {
+        Some(Value::Array(types)) => {
+            if !types.contains(&null) {
+                types.push(null);
+            }
+        }
+        Some(type_) if *type_ != null => {
+            let type_ = type_.take();
+            map.insert("type".to_owned(), Value::Array(vec![type_, null]));
+        }
+        Some(_) => {}
+        None => {
+            let is_nullable = match map.get("anyOf") {
+                Some(Value::Array(variants)) => variants.iter().any(|variant| variant["type"] == null),
+                _ => false,
+            };
+            if !is_nullable {
+                let inner = std::mem::take(map);
+                map.insert("anyOf".to_owned(), json!([Value::Object(inner), {"type": "null"}]));
+            }
+        }
+    }
+}
+
+/// Converts a `oneOf` entry into an Anthropic Messages API tool definition
+pub(crate) fn anthropic_function_schema(function_schema: &Value) -> Value {
+    let mut tool = Map::new();
+    tool.insert(
+        "name".to_owned(),
+        function_schema["properties"]["function_name"]["const"].clone(),
+    );
+    if let Some(description) = function_schema.get("description") {
+        tool.insert("description".to_owned(), description.clone());
+    }
+    tool.insert("input_schema".to_owned(), parameters_schema(function_schema));
+    Value::Object(tool)
+}
+
+/// Converts a `oneOf` entry into a Model Context Protocol `tools/list` entry
+#[cfg(feature = "mcp")]
+pub(crate) fn mcp_function_schema(function_schema: &Value) -> Value {
+    let mut tool = Map::new();
+    tool.insert(
+        "name".to_owned(),
+        function_schema["properties"]["function_name"]["const"].clone(),
+    );
+    if let Some(description) = function_schema.get("description") {
+        tool.insert("description".to_owned(), description.clone());
+    }
+    tool.insert("inputSchema".to_owned(), parameters_schema(function_schema));
+    Value::Object(tool)
+}
+
+/// Converts a `oneOf` entry into a Google Gemini function declaration
+pub(crate) fn gemini_function_schema(function_schema: &Value) -> Value {
+    let mut declaration = Map::new();
+    declaration.insert(
+        "name".to_owned(),
+        function_schema["properties"]["function_name"]["const"].clone(),
+    );
+    if let Some(description) = function_schema.get("description") {
+        declaration.insert("description".to_owned(), description.clone());
+    }
+    let parameters = parameters_schema(function_schema);
+    let definitions = parameters
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    declaration.insert(
+        "parameters".to_owned(),
+        gemini_schema(parameters, &definitions, &mut Vec::new()),
+    );
+    Value::Object(declaration)
+}
+
+/// The keywords of the OpenAPI schema subset Gemini accepts
+const GEMINI_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "description",
+    "nullable",
+    "enum",
+    "required",
+    "minimum",
+    "maximum",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "pattern",
+];
+
+/// Rewrites a json schema into the OpenAPI subset Gemini accepts. `$ref`s are inlined, except
+/// recursive ones which become an empty schema. `const` becomes a single value `enum`, a union with
+/// `null` becomes `nullable` and `oneOf` becomes `anyOf`. Other keywords are dropped.
+fn gemini_schema(schema: Value, definitions: &Map<String, Value>, resolving: &mut Vec<String>) -> Value {
+    let Value::Object(map) = schema else {
+        return schema;
+    };
+    if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/").to_owned();
+        let (Some(definition), false) = (definitions.get(&name), resolving.contains(&name)) else {
+            return Value::Object(Map::new());
+        };
+        resolving.push(name);
+        let mut inlined = gemini_schema(definition.clone(), definitions, resolving);
+        resolving.pop();
+        if let (Value::Object(inlined), Some(description)) = (&mut inlined, map.get("description")) {
+            inlined.insert("description".to_owned(), description.clone());
+        }
+        return inlined;
+    }
+    let mut gemini = Map::new();
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("properties", Value::Object(properties)) => {
+                let properties = properties
+                    .into_iter()
+                    .map(|(name, property)| (name, gemini_schema(property, definitions, resolving)))
+                    .collect();
+                gemini.insert(key, Value::Object(properties));
+            }
+            ("items", items) => {
+                gemini.insert(key, gemini_schema(items, definitions, resolving));
+            }
+            ("anyOf" | "oneOf", Value::Array(variants)) => {
+                let mut variants: Vec<Value> = variants
+                    .into_iter()
+                    .filter(|variant| {
+                        let is_null = variant.get("type").is_some_and(|type_| type_ == "null");
+                        if is_null {
+                            gemini.insert("nullable".to_owned(), Value::Bool(true));
+                        }
+                        !is_null
+                    })
+                    .map(|variant| gemini_schema(variant, definitions, resolving))
+                    .collect();
+                if variants.len() == 1 {
+                    if let Value::Object(variant) = variants.remove(0) {
+                        for (key, value) in variant {
+                            gemini.entry(key).or_insert(value);
+                        }
+                    }
+                } else {
+                    gemini.insert("anyOf".to_owned(), Value::Array(variants));
+                }
+            }
+            ("type", Value::Array(types)) => {
+                if types.iter().any(|type_| type_ == "null") {
+                    gemini.insert("nullable".to_owned(), Value::Bool(true));
+                }
+                if let Some(type_) = types.into_iter().find(|type_| type_ != "null") {
+                    gemini.insert(key, type_);
+                }
+            }
+            ("const", value) => {
+                gemini.insert("enum".to_owned(), Value::Array(vec![value]));
+            }
+            (keyword, value) if GEMINI_KEYWORDS.contains(&keyword) => {
+                gemini.insert(key, value);
+            }
+            _ => {}
+        }
+    }
+    Value::Object(gemini)
+}
+
+/// The parts of a `oneOf` entry the llm sees, with the keys of every object sorted, so the
+/// result is the same regardless of the order the schema was built in
+pub(crate) fn contract_function_schema(function_schema: &Value) -> Value {
+    let mut function = Map::new();
+    function.insert(
+        "name".to_owned(),
+        function_schema["properties"]["function_name"]["const"].clone(),
+    );
+    for key in ["description", "returns"] {
+        if let Some(value) = function_schema.get(key) {
+            function.insert(key.to_owned(), value.clone());
+        }
+    }
+    function.insert("parameters".to_owned(), parameters_schema(function_schema));
+    sort_keys(Value::Object(function))
+}
+
+/// The contract snapshot document of the `functions` made by [contract_function_schema], sorted by
+/// name
+pub(crate) fn contract_snapshot(functions: impl Iterator<Item = Value>) -> Value {
+    let mut functions: Vec<Value> = functions.collect();
+    functions.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    json!({ "functions": functions })
+}
+
+pub(crate) fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{FunctionCallArgs, Tool};
+
+/// A call of a [crate::ToolBoxLocal] matched to the tool that has the function, but not run yet,
+/// see [crate::ToolBoxLocal::resolve]. Run it with [crate::ToolBoxLocal::execute].
+pub struct ResolvedCallLocal<'a, O, E> {
+    pub(crate) tool: &'a dyn Tool<O, E>,
+    pub(crate) function_call: FunctionCallArgs,
+}
+
+impl<'a, O, E> ResolvedCallLocal<'a, O, E> {
+    /// The tool that has the function.
+    pub fn tool(&self) -> &'a dyn Tool<O, E> {
+        self.tool
+    }
+
+    /// The call that will be run.
+    pub fn function_call(&self) -> &FunctionCallArgs {
+        &self.function_call
+    }
+}
+
+/// A call of a [crate::ToolBox] matched to the tool that has the function, but not run yet, see
+/// [crate::ToolBox::resolve]. Run it with [crate::ToolBox::execute].
+pub struct ResolvedCall<'a, O, E> {
+    pub(crate) tool: &'a (dyn Tool<O, E> + Send + Sync),
+    pub(crate) function_call: FunctionCallArgs,
+}
+
+impl<'a, O, E> ResolvedCall<'a, O, E> {
+    /// The tool that has the function.
+    pub fn tool(&self) -> &'a (dyn Tool<O, E> + Send + Sync) {
+        self.tool
+    }
+
+    /// The call that will be run.
+    pub fn function_call(&self) -> &FunctionCallArgs {
+        &self.function_call
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Statistics for calls to a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FunctionStats {
+    /// number of calls
+    pub count: u64,
+    /// total time spent in all calls
+    pub total: Duration,
+    /// time spent in the slowest call
+    pub max: Duration,
+}
+
+/// Records the statistics for calls to each function of a toolbox. The entry for each function is
+/// created when the tool is added, so recording a call only uses atomics.
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    functions: HashMap<&'static str, FunctionStatsRecorder>,
+}
+
+#[derive(Default)]
+struct FunctionStatsRecorder {
+    count: AtomicU64,
+    total_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl StatsRecorder {
+    pub(crate) fn add(&mut self, function_names: &[&'static str]) {
+        for function_name in function_names {
+            self.functions.insert(function_name, FunctionStatsRecorder::default());
+        }
+    }
+
+    pub(crate) fn remove(&mut self, function_names: &[&'static str]) {
+        for function_name in function_names {
+            self.functions.remove(function_name);
+        }
+    }
+
+    pub(crate) fn record(&self, function_name: &str, elapsed: Duration) {
+        let Some(function) = self.functions.get(function_name) else {
+            return;
+        };
+        let elapsed_nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        function.count.fetch_add(1, Ordering::Relaxed);
+        function.total_nanos.fetch_add(elapsed_nanos, Ordering::Relaxed);
+        function.max_nanos.fetch_max(elapsed_nanos, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HashMap<&'static str, FunctionStats> {
+        self.functions
+            .iter()
+            .map(|(function_name, function)| {
+                (
+                    *function_name,
+                    FunctionStats {
+                        count: function.count.load(Ordering::Relaxed),
+                        total: Duration::from_nanos(function.total_nanos.load(Ordering::Relaxed)),
+                        max: Duration::from_nanos(function.max_nanos.load(Ordering::Relaxed)),
+                    },
+                )
+            })
+            .collect()
+    }
+}
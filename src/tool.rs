@@ -7,10 +7,55 @@ use crate::FunctionCallError;
 pub trait Tool<T, E> {
     fn function_names(&self) -> &[&'static str];
 
-    /// The schema for functions available to call for this tool
-    fn schema(&self) -> &'static Map<String, Value>;
+    /// The description of each function, in the same order as `function_names`. Provided with the
+    /// doc comment on each `#[tool_part]` method. Empty by default, the descriptions are then only
+    /// in the `schema`.
+    fn descriptions(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// The schema for functions available to call for this tool. Tools created with `#[tool]`
+    /// return a `'static` schema that is built once. A tool whose schema depends on runtime data,
+    /// e.g. a parameter enum of the files found at startup, can build the schema when it is
+    /// constructed and return a reference to it, at the cost of the schema living as long as the
+    /// tool rather than being shared.
+    fn schema(&self) -> &Map<String, Value>;
+
+    /// Description of the tool as a whole. Provided with the doc comment on the `#[tool]` impl
+    /// block.
+    fn tool_description(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The group this tool belongs to e.g. "filesystem". Provided with `#[tool(group = "...")]` and
+    /// added to the schema of each function as `x-group`.
+    fn group(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether calling the function `function_name` more than once has the same effect as calling
+    /// it once, so it is safe to retry. Provided with `#[tool_part(idempotent)]`.
+    fn is_idempotent(&self, _function_name: &str) -> bool {
+        false
+    }
+
+    /// Description of what the function `function_name` returns. Provided with
+    /// `#[tool_part(returns = "...")]`.
+    fn returns_description(&self, _function_name: &str) -> Option<&'static str> {
+        None
+    }
+
+    /// Serializes the `output` of calling the function `function_name` to json. Tools created with
+    /// `#[tool(json_output)]` implement this, otherwise it always returns an error.
+    fn output_to_json(&self, function_name: &str, _output: &T) -> Result<Value, FunctionCallError> {
+        Err(FunctionCallError::output_serialization(
+            function_name.to_owned(),
+            "The tool does not support serializing its output".to_owned(),
+        ))
+    }
 
     /// Runs the tool. This can never be called directly.
+    #[allow(clippy::type_complexity)]
     fn call_function<'life0, 'life1, 'async_trait>(
         &'life0 self,
         name: &'life1 str,
@@ -1,6 +1,29 @@
+use std::any::Any;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use futures_timer::Delay;
+use futures_util::future::{join_all, select, Either};
+use futures_util::stream::{FuturesUnordered, Stream};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-use crate::{utils::unwrap_match, FunctionCallError, FunctionCallParsingError, Tool};
+#[cfg(feature = "stats")]
+use crate::stats::{FunctionStats, StatsRecorder};
+#[cfg(feature = "validation")]
+use crate::validation::ValidatorCache;
+use crate::{
+    providers,
+    utils::{closest_name, unwrap_match},
+    AddToolError, FunctionCallError, FunctionCallParsingError, ResolvedCall, ResolvedCallLocal, Tool,
+    ToolBoxLocalView, ToolBoxView,
+};
+
+type OnCall = Box<dyn Fn(&str, &Map<String, Value>)>;
+type OnCallSync = Box<dyn Fn(&str, &Map<String, Value>) + Send + Sync>;
+type OnResult<O, E> = Box<dyn Fn(&str, &Result<Result<O, E>, FunctionCallError>, Duration)>;
+type OnResultSync<O, E> = Box<dyn Fn(&str, &Result<Result<O, E>, FunctionCallError>, Duration) + Send + Sync>;
 
 /// A toolbox is a collection of tools that can be called by name with arguments. [Tool] does
 /// not need to be Send or Sync, see [ToolBox] if needed.
@@ -9,6 +32,26 @@ pub struct ToolBoxLocal<O, E> {
     all_tools: Vec<Box<dyn Tool<O, E>>>,
     /// schema to be sent to the llm
     schema: Map<String, Value>,
+    /// call statistics for each function
+    #[cfg(feature = "stats")]
+    stats: StatsRecorder,
+    /// compiled parameter validators for each function
+    #[cfg(feature = "validation")]
+    validators: ValidatorCache,
+    /// text put before and after every function description in the provider schemas
+    description_wrapper: (String, String),
+    /// whether calls with parameters the function does not take are rejected
+    reject_unknown_parameters: bool,
+    /// called with the function name and parameters before each call
+    on_call: Option<OnCall>,
+    /// called with the function name, result and duration after each call
+    on_result: Option<OnResult<O, E>>,
+}
+
+impl<O, E> Default for ToolBoxLocal<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<O, E> ToolBoxLocal<O, E> {
@@ -16,32 +59,258 @@ impl<O, E> ToolBoxLocal<O, E> {
         Self {
             all_tools: Vec::new(),
             schema: Map::new(),
+            #[cfg(feature = "stats")]
+            stats: StatsRecorder::default(),
+            #[cfg(feature = "validation")]
+            validators: ValidatorCache::default(),
+            description_wrapper: (String::new(), String::new()),
+            reject_unknown_parameters: false,
+            on_call: None,
+            on_result: None,
+        }
+    }
+
+    /// Rejects calls with parameters the function does not take, including any parameters for a
+    /// function that takes none, with [FunctionCallParsingError::UnknownParameters]. By default
+    /// such parameters are ignored.
+    pub fn with_reject_unknown_parameters(mut self, reject_unknown_parameters: bool) -> Self {
+        self.reject_unknown_parameters = reject_unknown_parameters;
+        self
+    }
+
+    /// Puts `prefix` before and `suffix` after every function description in the provider schemas
+    /// e.g. [`Self::openai_schema`]. The descriptions of the tools themselves are unchanged.
+    pub fn with_description_wrapper(mut self, prefix: impl Into<String>, suffix: impl Into<String>) -> Self {
+        self.description_wrapper = (prefix.into(), suffix.into());
+        self
+    }
+
+    /// Sets a hook called with the function name and parameters before each call, e.g. to log
+    /// calls or open a tracing span.
+    pub fn set_on_call(&mut self, on_call: impl Fn(&str, &Map<String, Value>) + 'static) {
+        self.on_call = Some(Box::new(on_call));
+    }
+
+    /// Sets a hook called with the function name, result and how long the call took after each
+    /// call, e.g. to record latency.
+    pub fn set_on_result(&mut self, on_result: impl Fn(&str, &Result<Result<O, E>, FunctionCallError>, Duration) + 'static) {
+        self.on_result = Some(Box::new(on_result));
+    }
+
+    /// Creates a toolbox with all the `tools`. If a tool has a function with the same name as an
+    /// earlier tool, will return Err with that tool and the name.
+    pub fn from_tools(tools: impl IntoIterator<Item = Box<dyn Tool<O, E>>>) -> Result<Self, AddToolError<Box<dyn Tool<O, E>>>> {
+        let mut toolbox = Self::new();
+        for tool in tools {
+            toolbox.add_boxed_tool(tool)?;
         }
+        Ok(toolbox)
+    }
+
+    pub fn builder() -> ToolBoxLocalBuilder<O, E> {
+        ToolBoxLocalBuilder::new()
     }
 
-    // todo add merge to allow merging toolboxes across crates
+    /// A view of this toolbox where the parameter `name` is always `value`, e.g. a request scoped
+    /// `tenant_id`. The parameter is hidden from the provider schemas of the view.
+    pub fn bind_parameter(&self, name: &str, value: Value) -> ToolBoxLocalView<'_, O, E> {
+        ToolBoxLocalView::new(self).bind_parameter(name, value)
+    }
 
     /// Adds the `tool` to this [`Toolbox`]. If a tool with the same name already exists, will return
-    /// Err with the tool.
-    pub fn add_tool<T: Tool<O, E> + 'static>(&mut self, tool: T) -> Result<(), T> {
-        for existing_function_name in self.all_tools.iter().map(|e| e.function_names()).flatten() {
-            for new_function_name in tool.function_names() {
-                if existing_function_name == new_function_name {
-                    return Err(tool);
-                }
-            }
+    /// Err with the tool and the name.
+    pub fn add_tool<T: Tool<O, E> + 'static>(&mut self, tool: T) -> Result<(), AddToolError<T>> {
+        if let Some(conflicting_name) = self.conflicting_function_name(tool.function_names()) {
+            return Err(AddToolError { tool, conflicting_name });
         }
-        self.schema.extend(tool.schema().clone());
-        self.all_tools.push(Box::new(tool));
+        self.push_tool(Box::new(tool));
         Ok(())
     }
 
+    /// Adds the boxed `tool` to this [`Toolbox`]. If a tool with the same name already exists, will
+    /// return Err with the tool and the name.
+    pub fn add_boxed_tool(&mut self, tool: Box<dyn Tool<O, E>>) -> Result<(), AddToolError<Box<dyn Tool<O, E>>>> {
+        if let Some(conflicting_name) = self.conflicting_function_name(tool.function_names()) {
+            return Err(AddToolError { tool, conflicting_name });
+        }
+        self.push_tool(tool);
+        Ok(())
+    }
+
+    /// Moves all the tools of `other` into this [`Toolbox`], e.g. to combine toolboxes from different
+    /// crates. If a function name exists in both, nothing is moved and will return Err with `other` and the name.
+    #[allow(clippy::result_large_err)]
+    pub fn merge(&mut self, other: Self) -> Result<(), AddToolError<Self>> {
+        let conflicting_name = other
+            .all_tools
+            .iter()
+            .find_map(|tool| self.conflicting_function_name(tool.function_names()));
+        if let Some(conflicting_name) = conflicting_name {
+            return Err(AddToolError { tool: other, conflicting_name });
+        }
+        for tool in other.all_tools {
+            self.push_tool(tool);
+        }
+        Ok(())
+    }
+
+    /// Removes the tool that has the function `function_name`, including all of the tool's other
+    /// functions. Returns true if a tool was removed.
+    pub fn remove_tool(&mut self, function_name: &str) -> bool {
+        let Some(index) = self
+            .all_tools
+            .iter()
+            .position(|tool| tool.function_names().contains(&function_name))
+        else {
+            return false;
+        };
+        let tool = self.all_tools.remove(index);
+        remove_from_schema(&mut self.schema, tool.function_names());
+        #[cfg(feature = "stats")]
+        self.stats.remove(tool.function_names());
+        #[cfg(feature = "validation")]
+        self.validators.remove(tool.function_names());
+        true
+    }
+
+    fn push_tool(&mut self, tool: Box<dyn Tool<O, E>>) {
+        extend_schema(&mut self.schema, tool.schema());
+        #[cfg(feature = "stats")]
+        self.stats.add(tool.function_names());
+        #[cfg(feature = "validation")]
+        self.validators.add(tool.function_names());
+        self.all_tools.push(tool);
+    }
+
+    /// The `oneOf` entries of all tools with the description wrapper applied
+    pub(crate) fn provider_function_schemas(&self) -> impl Iterator<Item = Value> + '_ {
+        let (prefix, suffix) = &self.description_wrapper;
+        self.all_tools
+            .iter()
+            .flat_map(|tool| providers::function_schemas(tool.schema()))
+            .map(move |function_schema| providers::wrap_description(function_schema, prefix, suffix))
+    }
+
+    /// The first of `function_names` that already exists in this toolbox
+    fn conflicting_function_name(&self, function_names: &[&str]) -> Option<String> {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .find(|existing_function_name| function_names.contains(existing_function_name))
+            .map(|existing_function_name| (*existing_function_name).to_owned())
+    }
+
     /// Calls the tool with the given name and parameters.
     pub async fn call_from_value(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
         let function_call = self.into_function_call_from_value(function_call)?;
         self.call_from_args(function_call).await
     }
 
+    /// Calls the tool with the given name and parameters, and while the call fails with a
+    /// [FunctionCallError], calls it again with the value `regenerate` produces from the error,
+    /// e.g. by sending the error back to the llm. Gives up after `max_retries` retries with the
+    /// last error. Errors of the function itself are not retried, see [Self::call_with_retry].
+    pub async fn call_with_feedback(
+        &self,
+        function_call: Value,
+        max_retries: usize,
+        mut regenerate: impl FnMut(&FunctionCallError) -> Value,
+    ) -> Result<Result<O, E>, FunctionCallError> {
+        let mut function_call = function_call;
+        let mut retries = 0;
+        loop {
+            match self.call_from_value(function_call).await {
+                Err(error) if retries < max_retries => {
+                    retries += 1;
+                    function_call = regenerate(&error);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Calls each tool call in `function_calls`, which is either a single call or a json array of
+    /// calls, as returned by llms that make several calls at once. The calls run concurrently and
+    /// the results are in the order of the calls.
+    pub async fn call_many_from_value(&self, function_calls: Value) -> Vec<Result<Result<O, E>, FunctionCallError>> {
+        let function_calls = match function_calls {
+            Value::Array(function_calls) => function_calls,
+            function_call => vec![function_call],
+        };
+        join_all(function_calls.into_iter().map(|function_call| self.call_from_value(function_call))).await
+    }
+
+    /// Calls the tool with the given name and parameters, blocking the current thread until the
+    /// call completes, so no async runtime is needed. Meant for tools whose functions are not
+    /// `async`, since an `async` function that relies on a runtime, e.g. `tokio::time::sleep`,
+    /// will panic or never complete.
+    pub fn call_from_value_blocking(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        futures_executor::block_on(self.call_from_value(function_call))
+    }
+
+    /// Calls the tool with the given name and parameters, failing with
+    /// [FunctionCallError::Timeout] if the call does not complete within `timeout`. The call is
+    /// dropped on expiry, so it only stops at an `.await` point.
+    pub async fn call_from_value_with_timeout(&self, function_call: Value, timeout: Duration) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        let call = std::pin::pin!(self.call_from_args(function_call));
+        match select(call, Delay::new(timeout)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(FunctionCallError::Timeout {
+                function_name,
+                elapsed: timeout,
+            }),
+        }
+    }
+
+    /// Calls the tool with the given name and parameters, failing with
+    /// [FunctionCallError::Cancelled] if `abort` completes first e.g. when the user interrupts the
+    /// agent. The call is dropped on abort, so it only stops at an `.await` point.
+    pub async fn call_from_value_cancellable(&self, function_call: Value, abort: impl Future<Output = ()>) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        let call = std::pin::pin!(self.call_from_args(function_call));
+        match select(call, std::pin::pin!(abort)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(FunctionCallError::Cancelled { function_name }),
+        }
+    }
+
+    /// Calls the tool from an Anthropic `tool_use` content block e.g.
+    /// `{"type": "tool_use", "id": .., "name": .., "input": {..}}`.
+    pub async fn call_from_anthropic(&self, tool_use: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_anthropic(tool_use)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool from an OpenAI tool call e.g.
+    /// `{"id": .., "type": "function", "function": {"name": .., "arguments": "{..}"}}`.
+    pub async fn call_from_openai(&self, tool_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_openai(tool_call)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool from the `params` of an MCP `tools/call` request e.g.
+    /// `{"name": .., "arguments": {..}}`.
+    #[cfg(feature = "mcp")]
+    pub async fn call_from_mcp(&self, params: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_mcp(params)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool with the given name and parameters, after validating the parameters against
+    /// the function's parameter schema. Unlike [`Self::call_from_value`], which only fails once a
+    /// parameter cannot be deserialized, the error points to the offending parameter.
+    #[cfg(feature = "validation")]
+    pub async fn call_from_value_validated(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        if let Some(tool) = self.tool_for(&function_call.function_name) {
+            self.validators.validate(tool.schema(), &function_call)?;
+        }
+        self.call_from_args(function_call).await
+    }
+
     /// Calls the tool with the given name and parameters.
     pub async fn call_from_str(&self, function_call: &str) -> Result<Result<O, E>, FunctionCallError> {
         let function_call = self.into_function_call_from_str(function_call)?;
@@ -49,32 +318,346 @@ impl<O, E> ToolBoxLocal<O, E> {
     }
 
     pub async fn call_from_args(&self, function_call: FunctionCallArgs) -> Result<Result<O, E>, FunctionCallError> {
-        for tool in &self.all_tools {
-            for function_name in tool.function_names() {
-                if *function_name == function_call.function_name {
-                    return tool
-                        .call_function(&function_call.function_name, function_call.parameters)
-                        .await
-                        .map_err(|err| err.into());
-                }
+        let resolved = self.resolve_args(function_call)?;
+        self.execute(resolved).await
+    }
+
+    /// Matches the call to the tool that has the function, without running it, e.g. so a person
+    /// can approve the call first. With the `validation` feature, the parameters are also validated
+    /// against the function's parameter schema. Run the call with [`Self::execute`].
+    pub fn resolve(&self, function_call: Value) -> Result<ResolvedCallLocal<'_, O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let resolved = self.resolve_args(function_call)?;
+        #[cfg(feature = "validation")]
+        self.validators.validate(resolved.tool.schema(), &resolved.function_call)?;
+        Ok(resolved)
+    }
+
+    fn resolve_args(&self, function_call: FunctionCallArgs) -> Result<ResolvedCallLocal<'_, O, E>, FunctionCallError> {
+        let Some(tool) = self.tool_for(&function_call.function_name) else {
+            return Err(self.function_not_found(function_call.function_name));
+        };
+        if self.reject_unknown_parameters {
+            reject_unknown_parameters(tool.schema(), &function_call)?;
+        }
+        Ok(ResolvedCallLocal { tool, function_call })
+    }
+
+    /// Runs a call matched with [`Self::resolve`].
+    pub async fn execute(&self, resolved: ResolvedCallLocal<'_, O, E>) -> Result<Result<O, E>, FunctionCallError> {
+        let ResolvedCallLocal { tool, function_call } = resolved;
+        if let Some(on_call) = &self.on_call {
+            on_call(&function_call.function_name, &function_call.parameters);
+        }
+        #[cfg(feature = "stats")]
+        let start = std::time::Instant::now();
+        let hook_start = self.on_result.is_some().then(std::time::Instant::now);
+        let result = tool
+            .call_function(&function_call.function_name, function_call.parameters)
+            .await;
+        #[cfg(feature = "stats")]
+        self.stats.record(&function_call.function_name, start.elapsed());
+        if let (Some(on_result), Some(hook_start)) = (&self.on_result, hook_start) {
+            on_result(&function_call.function_name, &result, hook_start.elapsed());
+        }
+        result
+    }
+
+    /// Calls the tool, calling it again while the function returns an error, up to `max_attempts`
+    /// calls in total. Only functions marked idempotent, see [Tool::is_idempotent], are retried,
+    /// others are called once, so side effects such as sending an email are never repeated.
+    pub async fn call_with_retry(&self, function_call: FunctionCallArgs, max_attempts: usize) -> Result<Result<O, E>, FunctionCallError> {
+        let is_idempotent = self
+            .tool_for(&function_call.function_name)
+            .is_some_and(|tool| tool.is_idempotent(&function_call.function_name));
+        let max_attempts = if is_idempotent { max_attempts.max(1) } else { 1 };
+        let mut attempt = 1;
+        loop {
+            let result = self.call_from_args(function_call.clone()).await?;
+            if result.is_ok() || attempt >= max_attempts {
+                return Ok(result);
             }
+            attempt += 1;
         }
-        Err(FunctionCallError::FunctionNotFound {
-            function_name: function_call.function_name,
-        })
+    }
+
+    /// Calls the tool with the given name and parameters, serializing the output to json. The tool
+    /// must support this, e.g. created with `#[tool(json_output)]`.
+    pub async fn call_to_json(&self, function_call: FunctionCallArgs) -> Result<Result<Value, E>, FunctionCallError> {
+        let resolved = self.resolve_args(function_call)?;
+        let tool = resolved.tool();
+        let function_name = resolved.function_call().function_name.clone();
+        match self.execute(resolved).await? {
+            Ok(output) => tool.output_to_json(&function_name, &output).map(Ok),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    /// Calls each of `function_calls` one after another in order, e.g. when a call depends on an
+    /// earlier call. Unlike [`Self::call_many_from_value`] and [`Self::run_stream`], which run the calls
+    /// concurrently, stops at the first call that fails with a [FunctionCallError]. Errors returned
+    /// by the functions themselves do not stop the sequence.
+    ///
+    /// The results are returned as a `Vec` with the [FunctionCallError] as the last result, rather
+    /// than as `Result<Vec<Result<O, E>>, FunctionCallError>`, so the results of the calls that
+    /// already ran, and may have had side effects, are not lost when a later call fails. If every
+    /// call is dispatched, there is one result for each call and none is a [FunctionCallError].
+    pub async fn call_sequence(
+        &self,
+        function_calls: impl IntoIterator<Item = FunctionCallArgs>,
+    ) -> Vec<Result<Result<O, E>, FunctionCallError>> {
+        let mut results = Vec::new();
+        for function_call in function_calls {
+            let result = self.call_from_args(function_call).await;
+            let is_error = result.is_err();
+            results.push(result);
+            if is_error {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Calls each of `function_calls` concurrently, yielding each call with its result in the
+    /// order the calls finish.
+    pub fn run_stream(
+        &self,
+        function_calls: impl IntoIterator<Item = FunctionCallArgs>,
+    ) -> impl Stream<Item = (FunctionCallArgs, Result<Result<O, E>, FunctionCallError>)> + '_ {
+        function_calls
+            .into_iter()
+            .map(|function_call| async move {
+                let result = self.call_from_args(function_call.clone()).await;
+                (function_call, result)
+            })
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    /// The number of tools in this toolbox.
+    pub fn len(&self) -> usize {
+        self.all_tools.len()
+    }
+
+    /// Returns true if this toolbox has no tools.
+    pub fn is_empty(&self) -> bool {
+        self.all_tools.is_empty()
+    }
+
+    /// The tools in this toolbox, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Tool<O, E>> {
+        self.all_tools.iter().map(|tool| &**tool)
+    }
+
+    /// The tool that has the function `function_name`, e.g. to inspect its schema before calling.
+    pub fn tool_for(&self, function_name: &str) -> Option<&dyn Tool<O, E>> {
+        self.all_tools
+            .iter()
+            .find(|tool| tool.function_names().contains(&function_name))
+            .map(|tool| &**tool)
+    }
+
+    /// Returns true if a function with the name `function_name` exists in this toolbox.
+    pub fn function_exists(&self, function_name: &str) -> bool {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .any(|existing_function_name| *existing_function_name == function_name)
+    }
+
+    /// Finds the function with the name `function_name`, ignoring ASCII case. Returns the name as
+    /// it exists in this toolbox.
+    pub fn find_function_case_insensitive(&self, function_name: &str) -> Option<&str> {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .find(|existing_function_name| existing_function_name.eq_ignore_ascii_case(function_name))
+            .copied()
+    }
+
+    /// [FunctionCallError::FunctionNotFound], suggesting a function with a similar name
+    fn function_not_found(&self, function_name: String) -> FunctionCallError {
+        let did_you_mean = closest_name(&function_name, self.all_tools.iter().flat_map(|tool| tool.function_names()).copied())
+            .map(ToOwned::to_owned);
+        FunctionCallError::FunctionNotFound { function_name, did_you_mean }
     }
 
     pub fn into_function_call_from_str(&self, input: &str) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-        into_function_call_from_str(input)
+        parse_function_call_str(input)
     }
 
     pub fn into_function_call_from_value(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-        into_function_call_from_value(input)
+        parse_function_call(input)
+    }
+
+    /// Parses an Anthropic `tool_use` content block, keeping the tool use `id`.
+    pub fn into_function_call_from_anthropic(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_anthropic(input)
+    }
+
+    /// Parses an OpenAI tool call, keeping the tool call `id`. The `arguments` are a json string.
+    pub fn into_function_call_from_openai(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_openai(input)
+    }
+
+    /// Parses the `params` of an MCP `tools/call` request. Missing `arguments` are no parameters.
+    #[cfg(feature = "mcp")]
+    pub fn into_function_call_from_mcp(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_mcp(input)
     }
 
     pub fn schema(&self) -> &Map<String, Value> {
         &self.schema
     }
+
+    /// Writes [`Self::schema`] to `path` as pretty-printed json, with the keys of every object
+    /// sorted so the file diffs cleanly when checked in. See [crate::write_schema].
+    pub fn export_schema(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        crate::write_schema(&self.schema, path)
+    }
+
+    /// The schema of each tool, in the order the tools were added. Unlike [`Self::schema`], which
+    /// is a merged copy, these are the tools' own schemas, so nothing is cloned.
+    pub fn per_tool_schemas(&self) -> impl Iterator<Item = &Map<String, Value>> {
+        self.all_tools.iter().map(|tool| tool.schema())
+    }
+
+    /// Each function name with its `oneOf` entry in [`Self::schema`], which has the description
+    /// and parameters of the function. e.g. for rendering a help menu.
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Value)> {
+        providers::function_schemas(&self.schema).filter_map(|function_schema| {
+            let function_name = function_schema["properties"]["function_name"]["const"].as_str()?;
+            Some((function_name, function_schema))
+        })
+    }
+
+    /// The `oneOf` entry in [`Self::schema`] of the function `function_name`, if it exists.
+    pub fn function_schema(&self, function_name: &str) -> Option<&Value> {
+        self.functions()
+            .find(|(name, _)| *name == function_name)
+            .map(|(_, function_schema)| function_schema)
+    }
+
+    /// The call count and latencies of each function, recorded by `call_from_*`.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> std::collections::HashMap<&'static str, FunctionStats> {
+        self.stats.snapshot()
+    }
+
+    /// A stable document of everything the llm sees, the name, description and parameter schema of
+    /// each function sorted by name, e.g. to snapshot in tests. A change to it is a change to the
+    /// contract with the llm.
+    pub fn contract_snapshot(&self) -> Value {
+        providers::contract_snapshot(
+            self.provider_function_schemas()
+                .map(|function_schema| providers::contract_function_schema(&function_schema)),
+        )
+    }
+
+    /// The schema as a list of OpenAI Chat Completions tool definitions, one for each function.
+    /// e.g. `{"type": "function", "function": {"name": .., "description": .., "parameters": ..}}`.
+    /// Nested types are inlined, except recursive ones, whose `$ref`s point at the `definitions`
+    /// kept in the `parameters`.
+    pub fn openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a list of OpenAI Chat Completions tool definitions with `"strict": true`, so
+    /// calls always follow the schema. Every object has `"additionalProperties": false` and all
+    /// properties required. Optional parameters and fields are nullable instead, since they can
+    /// no longer be omitted. Free-form maps, e.g. a `HashMap<String, u32>` parameter, are not
+    /// supported by strict mode and are left open. Recursive types keep their `$ref`s into the
+    /// `definitions` of the `parameters`.
+    pub fn strict_openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::strict_openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a list of Anthropic Messages API tool definitions, one for each function, in
+    /// the order the tools were added. e.g. `{"name": .., "description": .., "input_schema": ..}`.
+    /// A recursive type is the only one not inlined, it is referenced from the `definitions` in
+    /// the `input_schema`.
+    pub fn anthropic_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::anthropic_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a Google Gemini tool, with a function declaration for each function.
+    /// e.g. `{"functionDeclarations": [{"name": .., "description": .., "parameters": ..}]}`. The
+    /// parameters are converted to the OpenAPI schema subset Gemini accepts, which has no `$ref`,
+    /// so the recursion of a recursive type is cut off with an empty schema.
+    pub fn gemini_schema(&self) -> Value {
+        let function_declarations = self
+            .provider_function_schemas()
+            .map(|function_schema| providers::gemini_function_schema(&function_schema))
+            .collect();
+        serde_json::json!({ "functionDeclarations": Value::Array(function_declarations) })
+    }
+
+    /// The schema as a list of MCP `tools/list` entries, one for each function.
+    /// e.g. `{"name": .., "description": .., "inputSchema": ..}`. The `inputSchema` of a function
+    /// with a recursive type keeps `definitions` for its `$ref`s.
+    #[cfg(feature = "mcp")]
+    pub fn mcp_tool_list(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::mcp_function_schema(&function_schema))
+            .collect()
+    }
+}
+
+impl<E> ToolBoxLocal<Box<dyn Any>, E> {
+    /// Calls the tool with the given name and parameters, downcasting the output to `T`. Fails
+    /// with [FunctionCallError::OutputTypeMismatch] if the output is not a `T`.
+    pub async fn call_and_downcast<T: 'static>(&self, function_call: Value) -> Result<Result<T, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        match self.call_from_args(function_call).await? {
+            Ok(output) => match output.downcast::<T>() {
+                Ok(output) => Ok(Ok(*output)),
+                Err(_) => Err(FunctionCallError::OutputTypeMismatch {
+                    function_name,
+                    expected: std::any::type_name::<T>().to_owned(),
+                }),
+            },
+            Err(error) => Ok(Err(error)),
+        }
+    }
+}
+
+/// Builds a [ToolBoxLocal]. The configuration of the toolbox is fixed once built.
+pub struct ToolBoxLocalBuilder<O, E> {
+    /// all the tools to add to the toolbox
+    all_tools: Vec<Box<dyn Tool<O, E>>>,
+}
+
+impl<O, E> Default for ToolBoxLocalBuilder<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, E> ToolBoxLocalBuilder<O, E> {
+    pub fn new() -> Self {
+        Self {
+            all_tools: Vec::new(),
+        }
+    }
+
+    /// Adds the `tool` to the toolbox. Name collisions are reported by [`Self::build`].
+    pub fn add_tool<T: Tool<O, E> + 'static>(mut self, tool: T) -> Self {
+        self.all_tools.push(Box::new(tool));
+        self
+    }
+
+    /// Builds the [ToolBoxLocal]. If a tool has a function with the same name as a previously added
+    /// tool, will return Err with that tool and the name.
+    #[allow(clippy::type_complexity)]
+    pub fn build(self) -> Result<ToolBoxLocal<O, E>, AddToolError<Box<dyn Tool<O, E>>>> {
+        ToolBoxLocal::from_tools(self.all_tools)
+    }
 }
 
 
@@ -85,6 +668,26 @@ pub struct ToolBox<O, E> {
     all_tools: Vec<Box<dyn Tool<O, E> + Send + Sync>>,
     /// schema to be sent to the llm
     schema: Map<String, Value>,
+    /// call statistics for each function
+    #[cfg(feature = "stats")]
+    stats: StatsRecorder,
+    /// compiled parameter validators for each function
+    #[cfg(feature = "validation")]
+    validators: ValidatorCache,
+    /// text put before and after every function description in the provider schemas
+    description_wrapper: (String, String),
+    /// whether calls with parameters the function does not take are rejected
+    reject_unknown_parameters: bool,
+    /// called with the function name and parameters before each call
+    on_call: Option<OnCallSync>,
+    /// called with the function name, result and duration after each call
+    on_result: Option<OnResultSync<O, E>>,
+}
+
+impl<O, E> Default for ToolBox<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<O, E> ToolBox<O, E> {
@@ -92,32 +695,279 @@ impl<O, E> ToolBox<O, E> {
         Self {
             all_tools: Vec::new(),
             schema: Map::new(),
+            #[cfg(feature = "stats")]
+            stats: StatsRecorder::default(),
+            #[cfg(feature = "validation")]
+            validators: ValidatorCache::default(),
+            description_wrapper: (String::new(), String::new()),
+            reject_unknown_parameters: false,
+            on_call: None,
+            on_result: None,
         }
     }
 
-    // todo add merge to allow merging toolboxes across crates
+    /// Rejects calls with parameters the function does not take, including any parameters for a
+    /// function that takes none, with [FunctionCallParsingError::UnknownParameters]. By default
+    /// such parameters are ignored.
+    pub fn with_reject_unknown_parameters(mut self, reject_unknown_parameters: bool) -> Self {
+        self.reject_unknown_parameters = reject_unknown_parameters;
+        self
+    }
+
+    /// Puts `prefix` before and `suffix` after every function description in the provider schemas
+    /// e.g. [`Self::openai_schema`]. The descriptions of the tools themselves are unchanged.
+    pub fn with_description_wrapper(mut self, prefix: impl Into<String>, suffix: impl Into<String>) -> Self {
+        self.description_wrapper = (prefix.into(), suffix.into());
+        self
+    }
+
+    /// Sets a hook called with the function name and parameters before each call, e.g. to log
+    /// calls or open a tracing span.
+    pub fn set_on_call(&mut self, on_call: impl Fn(&str, &Map<String, Value>) + Send + Sync + 'static) {
+        self.on_call = Some(Box::new(on_call));
+    }
+
+    /// Sets a hook called with the function name, result and how long the call took after each
+    /// call, e.g. to record latency.
+    pub fn set_on_result(&mut self, on_result: impl Fn(&str, &Result<Result<O, E>, FunctionCallError>, Duration) + Send + Sync + 'static) {
+        self.on_result = Some(Box::new(on_result));
+    }
+
+    /// Creates a toolbox with all the `tools`. If a tool has a function with the same name as an
+    /// earlier tool, will return Err with that tool and the name.
+    pub fn from_tools(tools: impl IntoIterator<Item = Box<dyn Tool<O, E> + Send + Sync>>) -> Result<Self, AddToolError<Box<dyn Tool<O, E> + Send + Sync>>> {
+        let mut toolbox = Self::new();
+        for tool in tools {
+            toolbox.add_boxed_tool(tool)?;
+        }
+        Ok(toolbox)
+    }
+
+    pub fn builder() -> ToolBoxBuilder<O, E> {
+        ToolBoxBuilder::new()
+    }
+
+    /// A view of this toolbox where the parameter `name` is always `value`, e.g. a request scoped
+    /// `tenant_id`. The parameter is hidden from the provider schemas of the view.
+    pub fn bind_parameter(&self, name: &str, value: Value) -> ToolBoxView<'_, O, E> {
+        ToolBoxView::new(self).bind_parameter(name, value)
+    }
 
     /// Adds the `tool` to this [`Toolbox`]. If a tool with the same name already exists, will return
-    /// Err with the tool.
-    pub fn add_tool<T: Tool<O, E> + Send + Sync + 'static>(&mut self, tool: T) -> Result<(), T> {
-        for existing_function_name in self.all_tools.iter().map(|e| e.function_names()).flatten() {
-            for new_function_name in tool.function_names() {
-                if existing_function_name == new_function_name {
-                    return Err(tool);
-                }
-            }
+    /// Err with the tool and the name.
+    pub fn add_tool<T: Tool<O, E> + Send + Sync + 'static>(&mut self, tool: T) -> Result<(), AddToolError<T>> {
+        if let Some(conflicting_name) = self.conflicting_function_name(tool.function_names()) {
+            return Err(AddToolError { tool, conflicting_name });
+        }
+        self.push_tool(Box::new(tool));
+        Ok(())
+    }
+
+    /// Adds the boxed `tool` to this [`Toolbox`]. If a tool with the same name already exists, will
+    /// return Err with the tool and the name.
+    pub fn add_boxed_tool(&mut self, tool: Box<dyn Tool<O, E> + Send + Sync>) -> Result<(), AddToolError<Box<dyn Tool<O, E> + Send + Sync>>> {
+        if let Some(conflicting_name) = self.conflicting_function_name(tool.function_names()) {
+            return Err(AddToolError { tool, conflicting_name });
+        }
+        self.push_tool(tool);
+        Ok(())
+    }
+
+    /// Moves all the tools of `other` into this [`Toolbox`], e.g. to combine toolboxes from different
+    /// crates. If a function name exists in both, nothing is moved and will return Err with `other` and the name.
+    #[allow(clippy::result_large_err)]
+    pub fn merge(&mut self, other: Self) -> Result<(), AddToolError<Self>> {
+        let conflicting_name = other
+            .all_tools
+            .iter()
+            .find_map(|tool| self.conflicting_function_name(tool.function_names()));
+        if let Some(conflicting_name) = conflicting_name {
+            return Err(AddToolError { tool: other, conflicting_name });
+        }
+        for tool in other.all_tools {
+            self.push_tool(tool);
         }
-        self.schema.extend(tool.schema().clone());
-        self.all_tools.push(Box::new(tool));
         Ok(())
     }
 
+    /// Removes the tool that has the function `function_name`, including all of the tool's other
+    /// functions. Returns true if a tool was removed.
+    pub fn remove_tool(&mut self, function_name: &str) -> bool {
+        let Some(index) = self
+            .all_tools
+            .iter()
+            .position(|tool| tool.function_names().contains(&function_name))
+        else {
+            return false;
+        };
+        let tool = self.all_tools.remove(index);
+        remove_from_schema(&mut self.schema, tool.function_names());
+        #[cfg(feature = "stats")]
+        self.stats.remove(tool.function_names());
+        #[cfg(feature = "validation")]
+        self.validators.remove(tool.function_names());
+        true
+    }
+
+    fn push_tool(&mut self, tool: Box<dyn Tool<O, E> + Send + Sync>) {
+        extend_schema(&mut self.schema, tool.schema());
+        #[cfg(feature = "stats")]
+        self.stats.add(tool.function_names());
+        #[cfg(feature = "validation")]
+        self.validators.add(tool.function_names());
+        self.all_tools.push(tool);
+    }
+
+    /// The `oneOf` entries of all tools with the description wrapper applied
+    pub(crate) fn provider_function_schemas(&self) -> impl Iterator<Item = Value> + '_ {
+        let (prefix, suffix) = &self.description_wrapper;
+        self.all_tools
+            .iter()
+            .flat_map(|tool| providers::function_schemas(tool.schema()))
+            .map(move |function_schema| providers::wrap_description(function_schema, prefix, suffix))
+    }
+
+    /// The first of `function_names` that already exists in this toolbox
+    fn conflicting_function_name(&self, function_names: &[&str]) -> Option<String> {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .find(|existing_function_name| function_names.contains(existing_function_name))
+            .map(|existing_function_name| (*existing_function_name).to_owned())
+    }
+
     /// Calls the tool with the given name and parameters.
     pub async fn call_from_value(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
         let function_call = self.into_function_call_from_value(function_call)?;
         self.call_from_args(function_call).await
     }
 
+    /// Calls the tool with the given name and parameters, and while the call fails with a
+    /// [FunctionCallError], calls it again with the value `regenerate` produces from the error,
+    /// e.g. by sending the error back to the llm. Gives up after `max_retries` retries with the
+    /// last error. Errors of the function itself are not retried, see [Self::call_with_retry].
+    pub async fn call_with_feedback(
+        &self,
+        function_call: Value,
+        max_retries: usize,
+        mut regenerate: impl FnMut(&FunctionCallError) -> Value,
+    ) -> Result<Result<O, E>, FunctionCallError> {
+        let mut function_call = function_call;
+        let mut retries = 0;
+        loop {
+            match self.call_from_value(function_call).await {
+                Err(error) if retries < max_retries => {
+                    retries += 1;
+                    function_call = regenerate(&error);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Calls each tool call in `function_calls`, which is either a single call or a json array of
+    /// calls, as returned by llms that make several calls at once. The calls run concurrently, see
+    /// [Self::call_many_concurrent], and the results are in the order of the calls.
+    pub async fn call_many_from_value(&self, function_calls: Value) -> Vec<Result<Result<O, E>, FunctionCallError>> {
+        let function_calls = match function_calls {
+            Value::Array(function_calls) => function_calls,
+            function_call => vec![function_call],
+        };
+        let mut parsing_errors = Vec::with_capacity(function_calls.len());
+        let function_calls: Vec<_> = function_calls
+            .into_iter()
+            .filter_map(|function_call| match self.into_function_call_from_value(function_call) {
+                Ok(function_call) => {
+                    parsing_errors.push(None);
+                    Some(function_call)
+                }
+                Err(error) => {
+                    parsing_errors.push(Some(error));
+                    None
+                }
+            })
+            .collect();
+        let mut results = self.call_many_concurrent(function_calls).await.into_iter();
+        parsing_errors
+            .into_iter()
+            .map(|parsing_error| match parsing_error {
+                Some(error) => Err(error.into()),
+                None => results.next().expect("a result for every parsed call"),
+            })
+            .collect()
+    }
+
+    /// Calls the tool with the given name and parameters, blocking the current thread until the
+    /// call completes, so no async runtime is needed. Meant for tools whose functions are not
+    /// `async`, since an `async` function that relies on a runtime, e.g. `tokio::time::sleep`,
+    /// will panic or never complete.
+    pub fn call_from_value_blocking(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        futures_executor::block_on(self.call_from_value(function_call))
+    }
+
+    /// Calls the tool with the given name and parameters, failing with
+    /// [FunctionCallError::Timeout] if the call does not complete within `timeout`. The call is
+    /// dropped on expiry, so it only stops at an `.await` point.
+    pub async fn call_from_value_with_timeout(&self, function_call: Value, timeout: Duration) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        let call = std::pin::pin!(self.call_from_args(function_call));
+        match select(call, Delay::new(timeout)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(FunctionCallError::Timeout {
+                function_name,
+                elapsed: timeout,
+            }),
+        }
+    }
+
+    /// Calls the tool with the given name and parameters, failing with
+    /// [FunctionCallError::Cancelled] if `abort` completes first e.g. when the user interrupts the
+    /// agent. The call is dropped on abort, so it only stops at an `.await` point.
+    pub async fn call_from_value_cancellable(&self, function_call: Value, abort: impl Future<Output = ()>) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        let call = std::pin::pin!(self.call_from_args(function_call));
+        match select(call, std::pin::pin!(abort)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(FunctionCallError::Cancelled { function_name }),
+        }
+    }
+
+    /// Calls the tool from an Anthropic `tool_use` content block e.g.
+    /// `{"type": "tool_use", "id": .., "name": .., "input": {..}}`.
+    pub async fn call_from_anthropic(&self, tool_use: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_anthropic(tool_use)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool from an OpenAI tool call e.g.
+    /// `{"id": .., "type": "function", "function": {"name": .., "arguments": "{..}"}}`.
+    pub async fn call_from_openai(&self, tool_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_openai(tool_call)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool from the `params` of an MCP `tools/call` request e.g.
+    /// `{"name": .., "arguments": {..}}`.
+    #[cfg(feature = "mcp")]
+    pub async fn call_from_mcp(&self, params: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_mcp(params)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool with the given name and parameters, after validating the parameters against
+    /// the function's parameter schema. Unlike [`Self::call_from_value`], which only fails once a
+    /// parameter cannot be deserialized, the error points to the offending parameter.
+    #[cfg(feature = "validation")]
+    pub async fn call_from_value_validated(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        if let Some(tool) = self.tool_for(&function_call.function_name) {
+            self.validators.validate(tool.schema(), &function_call)?;
+        }
+        self.call_from_args(function_call).await
+    }
+
     /// Calls the tool with the given name and parameters.
     pub async fn call_from_str(&self, function_call: &str) -> Result<Result<O, E>, FunctionCallError> {
         let function_call = self.into_function_call_from_str(function_call)?;
@@ -125,88 +975,563 @@ impl<O, E> ToolBox<O, E> {
     }
 
     pub async fn call_from_args(&self, function_call: FunctionCallArgs) -> Result<Result<O, E>, FunctionCallError> {
-        for tool in &self.all_tools {
-            for function_name in tool.function_names() {
-                if *function_name == function_call.function_name {
-                    return tool
-                        .call_function(&function_call.function_name, function_call.parameters)
-                        .await
-                        .map_err(|err| err.into());
-                }
+        let resolved = self.resolve_args(function_call)?;
+        self.execute(resolved).await
+    }
+
+    /// Matches the call to the tool that has the function, without running it, e.g. so a person
+    /// can approve the call first. With the `validation` feature, the parameters are also validated
+    /// against the function's parameter schema. Run the call with [`Self::execute`].
+    pub fn resolve(&self, function_call: Value) -> Result<ResolvedCall<'_, O, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let resolved = self.resolve_args(function_call)?;
+        #[cfg(feature = "validation")]
+        self.validators.validate(resolved.tool.schema(), &resolved.function_call)?;
+        Ok(resolved)
+    }
+
+    fn resolve_args(&self, function_call: FunctionCallArgs) -> Result<ResolvedCall<'_, O, E>, FunctionCallError> {
+        let Some(tool) = self.tool_for(&function_call.function_name) else {
+            return Err(self.function_not_found(function_call.function_name));
+        };
+        if self.reject_unknown_parameters {
+            reject_unknown_parameters(tool.schema(), &function_call)?;
+        }
+        Ok(ResolvedCall { tool, function_call })
+    }
+
+    /// Runs a call matched with [`Self::resolve`].
+    pub async fn execute(&self, resolved: ResolvedCall<'_, O, E>) -> Result<Result<O, E>, FunctionCallError> {
+        let ResolvedCall { tool, function_call } = resolved;
+        if let Some(on_call) = &self.on_call {
+            on_call(&function_call.function_name, &function_call.parameters);
+        }
+        #[cfg(feature = "stats")]
+        let start = std::time::Instant::now();
+        let hook_start = self.on_result.is_some().then(std::time::Instant::now);
+        let result = tool
+            .call_function(&function_call.function_name, function_call.parameters)
+            .await;
+        #[cfg(feature = "stats")]
+        self.stats.record(&function_call.function_name, start.elapsed());
+        if let (Some(on_result), Some(hook_start)) = (&self.on_result, hook_start) {
+            on_result(&function_call.function_name, &result, hook_start.elapsed());
+        }
+        result
+    }
+
+    /// Calls the tool, calling it again while the function returns an error, up to `max_attempts`
+    /// calls in total. Only functions marked idempotent, see [Tool::is_idempotent], are retried,
+    /// others are called once, so side effects such as sending an email are never repeated.
+    pub async fn call_with_retry(&self, function_call: FunctionCallArgs, max_attempts: usize) -> Result<Result<O, E>, FunctionCallError> {
+        let is_idempotent = self
+            .tool_for(&function_call.function_name)
+            .is_some_and(|tool| tool.is_idempotent(&function_call.function_name));
+        let max_attempts = if is_idempotent { max_attempts.max(1) } else { 1 };
+        let mut attempt = 1;
+        loop {
+            let result = self.call_from_args(function_call.clone()).await?;
+            if result.is_ok() || attempt >= max_attempts {
+                return Ok(result);
             }
+            attempt += 1;
         }
-        Err(FunctionCallError::FunctionNotFound {
-            function_name: function_call.function_name,
-        })
+    }
+
+    /// Calls the tool with the given name and parameters, serializing the output to json. The tool
+    /// must support this, e.g. created with `#[tool(json_output)]`.
+    pub async fn call_to_json(&self, function_call: FunctionCallArgs) -> Result<Result<Value, E>, FunctionCallError> {
+        let resolved = self.resolve_args(function_call)?;
+        let tool = resolved.tool();
+        let function_name = resolved.function_call().function_name.clone();
+        match self.execute(resolved).await? {
+            Ok(output) => tool.output_to_json(&function_name, &output).map(Ok),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    /// Calls each of `function_calls` one after another in order, e.g. when a call depends on an
+    /// earlier call. Unlike [`Self::call_many_from_value`] and [`Self::run_stream`], which run the calls
+    /// concurrently, stops at the first call that fails with a [FunctionCallError]. Errors returned
+    /// by the functions themselves do not stop the sequence.
+    ///
+    /// The results are returned as a `Vec` with the [FunctionCallError] as the last result, rather
+    /// than as `Result<Vec<Result<O, E>>, FunctionCallError>`, so the results of the calls that
+    /// already ran, and may have had side effects, are not lost when a later call fails. If every
+    /// call is dispatched, there is one result for each call and none is a [FunctionCallError].
+    pub async fn call_sequence(
+        &self,
+        function_calls: impl IntoIterator<Item = FunctionCallArgs>,
+    ) -> Vec<Result<Result<O, E>, FunctionCallError>> {
+        let mut results = Vec::new();
+        for function_call in function_calls {
+            let result = self.call_from_args(function_call).await;
+            let is_error = result.is_err();
+            results.push(result);
+            if is_error {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Calls each of `function_calls` concurrently, returning the results in the order of the
+    /// calls. The calls are polled together on the task awaiting this future, so they overlap while
+    /// waiting e.g. on I/O, but do not run in parallel. A call that blocks or is CPU-bound holds up
+    /// the others, run such work with e.g. `tokio::task::spawn_blocking` inside the tool.
+    pub async fn call_many_concurrent(
+        &self,
+        function_calls: impl IntoIterator<Item = FunctionCallArgs>,
+    ) -> Vec<Result<Result<O, E>, FunctionCallError>> {
+        join_all(function_calls.into_iter().map(|function_call| self.call_from_args(function_call))).await
+    }
+
+    /// Calls each of `function_calls` concurrently, yielding each call with its result in the
+    /// order the calls finish.
+    pub fn run_stream(
+        &self,
+        function_calls: impl IntoIterator<Item = FunctionCallArgs>,
+    ) -> impl Stream<Item = (FunctionCallArgs, Result<Result<O, E>, FunctionCallError>)> + '_ {
+        function_calls
+            .into_iter()
+            .map(|function_call| async move {
+                let result = self.call_from_args(function_call.clone()).await;
+                (function_call, result)
+            })
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    /// The number of tools in this toolbox.
+    pub fn len(&self) -> usize {
+        self.all_tools.len()
+    }
+
+    /// Returns true if this toolbox has no tools.
+    pub fn is_empty(&self) -> bool {
+        self.all_tools.is_empty()
+    }
+
+    /// The tools in this toolbox, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &(dyn Tool<O, E> + Send + Sync)> {
+        self.all_tools.iter().map(|tool| &**tool)
+    }
+
+    /// The tool that has the function `function_name`, e.g. to inspect its schema before calling.
+    pub fn tool_for(&self, function_name: &str) -> Option<&(dyn Tool<O, E> + Send + Sync)> {
+        self.all_tools
+            .iter()
+            .find(|tool| tool.function_names().contains(&function_name))
+            .map(|tool| &**tool)
+    }
+
+    /// Returns true if a function with the name `function_name` exists in this toolbox.
+    pub fn function_exists(&self, function_name: &str) -> bool {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .any(|existing_function_name| *existing_function_name == function_name)
+    }
+
+    /// Finds the function with the name `function_name`, ignoring ASCII case. Returns the name as
+    /// it exists in this toolbox.
+    pub fn find_function_case_insensitive(&self, function_name: &str) -> Option<&str> {
+        self.all_tools
+            .iter()
+            .flat_map(|e| e.function_names())
+            .find(|existing_function_name| existing_function_name.eq_ignore_ascii_case(function_name))
+            .copied()
+    }
+
+    /// [FunctionCallError::FunctionNotFound], suggesting a function with a similar name
+    fn function_not_found(&self, function_name: String) -> FunctionCallError {
+        let did_you_mean = closest_name(&function_name, self.all_tools.iter().flat_map(|tool| tool.function_names()).copied())
+            .map(ToOwned::to_owned);
+        FunctionCallError::FunctionNotFound { function_name, did_you_mean }
     }
 
     pub fn into_function_call_from_str(&self, input: &str) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-        into_function_call_from_str(input)
+        parse_function_call_str(input)
     }
 
     pub fn into_function_call_from_value(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-        into_function_call_from_value(input)
+        parse_function_call(input)
+    }
+
+    /// Parses an Anthropic `tool_use` content block, keeping the tool use `id`.
+    pub fn into_function_call_from_anthropic(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_anthropic(input)
+    }
+
+    /// Parses an OpenAI tool call, keeping the tool call `id`. The `arguments` are a json string.
+    pub fn into_function_call_from_openai(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_openai(input)
+    }
+
+    /// Parses the `params` of an MCP `tools/call` request. Missing `arguments` are no parameters.
+    #[cfg(feature = "mcp")]
+    pub fn into_function_call_from_mcp(&self, input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+        into_function_call_from_mcp(input)
     }
 
     pub fn schema(&self) -> &Map<String, Value> {
         &self.schema
     }
+
+    /// Writes [`Self::schema`] to `path` as pretty-printed json, with the keys of every object
+    /// sorted so the file diffs cleanly when checked in. See [crate::write_schema].
+    pub fn export_schema(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        crate::write_schema(&self.schema, path)
+    }
+
+    /// The schema of each tool, in the order the tools were added. Unlike [`Self::schema`], which
+    /// is a merged copy, these are the tools' own schemas, so nothing is cloned.
+    pub fn per_tool_schemas(&self) -> impl Iterator<Item = &Map<String, Value>> {
+        self.all_tools.iter().map(|tool| tool.schema())
+    }
+
+    /// Each function name with its `oneOf` entry in [`Self::schema`], which has the description
+    /// and parameters of the function. e.g. for rendering a help menu.
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Value)> {
+        providers::function_schemas(&self.schema).filter_map(|function_schema| {
+            let function_name = function_schema["properties"]["function_name"]["const"].as_str()?;
+            Some((function_name, function_schema))
+        })
+    }
+
+    /// The `oneOf` entry in [`Self::schema`] of the function `function_name`, if it exists.
+    pub fn function_schema(&self, function_name: &str) -> Option<&Value> {
+        self.functions()
+            .find(|(name, _)| *name == function_name)
+            .map(|(_, function_schema)| function_schema)
+    }
+
+    /// The call count and latencies of each function, recorded by `call_from_*`.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> std::collections::HashMap<&'static str, FunctionStats> {
+        self.stats.snapshot()
+    }
+
+    /// A stable document of everything the llm sees, the name, description and parameter schema of
+    /// each function sorted by name, e.g. to snapshot in tests. A change to it is a change to the
+    /// contract with the llm.
+    pub fn contract_snapshot(&self) -> Value {
+        providers::contract_snapshot(
+            self.provider_function_schemas()
+                .map(|function_schema| providers::contract_function_schema(&function_schema)),
+        )
+    }
+
+    /// The schema as a list of OpenAI Chat Completions tool definitions, one for each function.
+    /// e.g. `{"type": "function", "function": {"name": .., "description": .., "parameters": ..}}`.
+    /// Nested types are inlined, except recursive ones, whose `$ref`s point at the `definitions`
+    /// kept in the `parameters`.
+    pub fn openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a list of OpenAI Chat Completions tool definitions with `"strict": true`, so
+    /// calls always follow the schema. Every object has `"additionalProperties": false` and all
+    /// properties required. Optional parameters and fields are nullable instead, since they can
+    /// no longer be omitted. Free-form maps, e.g. a `HashMap<String, u32>` parameter, are not
+    /// supported by strict mode and are left open. Recursive types keep their `$ref`s into the
+    /// `definitions` of the `parameters`.
+    pub fn strict_openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::strict_openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a list of Anthropic Messages API tool definitions, one for each function, in
+    /// the order the tools were added. e.g. `{"name": .., "description": .., "input_schema": ..}`.
+    /// A recursive type is the only one not inlined, it is referenced from the `definitions` in
+    /// the `input_schema`.
+    pub fn anthropic_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::anthropic_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The schema as a Google Gemini tool, with a function declaration for each function.
+    /// e.g. `{"functionDeclarations": [{"name": .., "description": .., "parameters": ..}]}`. The
+    /// parameters are converted to the OpenAPI schema subset Gemini accepts, which has no `$ref`,
+    /// so the recursion of a recursive type is cut off with an empty schema.
+    pub fn gemini_schema(&self) -> Value {
+        let function_declarations = self
+            .provider_function_schemas()
+            .map(|function_schema| providers::gemini_function_schema(&function_schema))
+            .collect();
+        serde_json::json!({ "functionDeclarations": Value::Array(function_declarations) })
+    }
+
+    /// The schema as a list of MCP `tools/list` entries, one for each function.
+    /// e.g. `{"name": .., "description": .., "inputSchema": ..}`. The `inputSchema` of a function
+    /// with a recursive type keeps `definitions` for its `$ref`s.
+    #[cfg(feature = "mcp")]
+    pub fn mcp_tool_list(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::mcp_function_schema(&function_schema))
+            .collect()
+    }
+}
+
+impl<E> ToolBox<Box<dyn Any>, E> {
+    /// Calls the tool with the given name and parameters, downcasting the output to `T`. Fails
+    /// with [FunctionCallError::OutputTypeMismatch] if the output is not a `T`.
+    pub async fn call_and_downcast<T: 'static>(&self, function_call: Value) -> Result<Result<T, E>, FunctionCallError> {
+        let function_call = self.into_function_call_from_value(function_call)?;
+        let function_name = function_call.function_name.clone();
+        match self.call_from_args(function_call).await? {
+            Ok(output) => match output.downcast::<T>() {
+                Ok(output) => Ok(Ok(*output)),
+                Err(_) => Err(FunctionCallError::OutputTypeMismatch {
+                    function_name,
+                    expected: std::any::type_name::<T>().to_owned(),
+                }),
+            },
+            Err(error) => Ok(Err(error)),
+        }
+    }
+}
+
+/// Builds a [ToolBox]. The configuration of the toolbox is fixed once built.
+pub struct ToolBoxBuilder<O, E> {
+    /// all the tools to add to the toolbox
+    all_tools: Vec<Box<dyn Tool<O, E> + Send + Sync>>,
+}
+
+impl<O, E> Default for ToolBoxBuilder<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, E> ToolBoxBuilder<O, E> {
+    pub fn new() -> Self {
+        Self {
+            all_tools: Vec::new(),
+        }
+    }
+
+    /// Adds the `tool` to the toolbox. Name collisions are reported by [`Self::build`].
+    pub fn add_tool<T: Tool<O, E> + Send + Sync + 'static>(mut self, tool: T) -> Self {
+        self.all_tools.push(Box::new(tool));
+        self
+    }
+
+    /// Builds the [ToolBox]. If a tool has a function with the same name as a previously added
+    /// tool, will return Err with that tool and the name.
+    #[allow(clippy::type_complexity)]
+    pub fn build(self) -> Result<ToolBox<O, E>, AddToolError<Box<dyn Tool<O, E> + Send + Sync>>> {
+        ToolBox::from_tools(self.all_tools)
+    }
 }
 
 //************************************************************************//
 
-fn into_function_call_from_str(input: &str) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-    let value =
-        serde_json::from_str::<Value>(input)
-            .ok()
-            .ok_or_else(|| FunctionCallParsingError::Parsing {
-                issue: "The tool call is not valid json".to_owned(),
-            })?;
-    into_function_call_from_value(value)
+/// Adds the functions in `tool_schema` to the toolbox `schema`, and hoists their definitions
+fn extend_schema(schema: &mut Map<String, Value>, tool_schema: &Map<String, Value>) {
+    for (key, value) in tool_schema {
+        match (schema.get_mut(key), value) {
+            (Some(Value::Array(functions)), Value::Array(new_functions)) if key == "oneOf" => {
+                functions.extend(new_functions.iter().cloned());
+            }
+            _ => {
+                schema.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    crate::cleaner::hoist_definitions(schema);
+}
+
+/// Removes the functions named `function_names` from the toolbox `schema`, and their definitions
+fn remove_from_schema(schema: &mut Map<String, Value>, function_names: &[&str]) {
+    if let Some(Value::Array(functions)) = schema.get_mut("oneOf") {
+        functions.retain(|function| {
+            !function_names.iter().any(|function_name| {
+                function["properties"]["function_name"]["const"] == *function_name
+            })
+        });
+    }
+    crate::cleaner::hoist_definitions(schema);
 }
 
-fn into_function_call_from_value(input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
-    let name = match input.get("function_name") {
-        Some(name) => name,
-        None => {
+/// Parses a tool call string e.g. `{"function_name": .., "parameters": {..}}`, without a toolbox,
+/// e.g. to route a call before the toolbox is available. See [parse_function_call].
+pub fn parse_function_call_str(input: &str) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    let value = serde_json::from_str::<Value>(input)?;
+    parse_function_call(value)
+}
+
+/// Parses a tool call e.g. `{"function_name": .., "parameters": {..}}`, without a toolbox. The
+/// function name is not checked against any tool.
+pub fn parse_function_call(input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    into_function_call_from_fields(input, "function_name", "parameters")
+}
+
+/// Parses an Anthropic `tool_use` content block e.g.
+/// `{"type": "tool_use", "id": .., "name": .., "input": {..}}`
+fn into_function_call_from_anthropic(input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    if let Some(type_) = input.get("type") {
+        if type_ != "tool_use" {
             return Err(FunctionCallParsingError::Parsing {
-                issue: format!(
-                    "The tool call is missing the `function_name` field in:\n{input}"
-                ),
+                issue: format!("The tool call `type` field is not `tool_use` in:\n{input}"),
             });
         }
+    }
+    into_function_call_from_fields(input, "name", "input")
+}
+
+/// Parses an OpenAI tool call e.g.
+/// `{"id": .., "type": "function", "function": {"name": .., "arguments": "{..}"}}`
+fn into_function_call_from_openai(mut input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    if let Some(type_) = input.get("type") {
+        if type_ != "function" {
+            return Err(FunctionCallParsingError::Parsing {
+                issue: format!("The tool call `type` field is not `function` in:\n{input}"),
+            });
+        }
+    }
+    let mut function = match input.get_mut("function") {
+        Some(Value::Object(function)) => std::mem::take(function),
+        _ => {
+            return Err(FunctionCallParsingError::Parsing {
+                issue: format!("The tool call is missing the `function` object in:\n{input}"),
+            });
+        }
+    };
+    // the arguments are double encoded, a json object in a string
+    if let Some(Value::String(arguments)) = function.get("arguments") {
+        let arguments = if arguments.trim().is_empty() {
+            Value::Object(Map::new())
+        } else {
+            serde_json::from_str::<Value>(arguments)?
+        };
+        function.insert("arguments".to_owned(), arguments);
+    }
+    if let Some(id) = input.get_mut("id").map(Value::take) {
+        function.insert("id".to_owned(), id);
+    }
+    into_function_call_from_fields(Value::Object(function), "name", "arguments")
+}
+
+#[cfg(feature = "mcp")]
+fn into_function_call_from_mcp(mut input: Value) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    if let Value::Object(map) = &mut input {
+        map.entry("arguments")
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    into_function_call_from_fields(input, "name", "arguments")
+}
+
+/// Errors if `function_call` has parameters that are not in the parameter schema of the function
+fn reject_unknown_parameters(
+    tool_schema: &Map<String, Value>,
+    function_call: &FunctionCallArgs,
+) -> Result<(), FunctionCallParsingError> {
+    let Some(function_schema) = providers::function_schemas(tool_schema).find(|function_schema| {
+        function_schema["properties"]["function_name"]["const"] == function_call.function_name
+    }) else {
+        return Ok(());
     };
-    let _ = match name.as_str() {
+    let properties = &function_schema["properties"]["parameters"]["properties"];
+    let parameter_names: Vec<String> = function_call
+        .parameters
+        .keys()
+        .filter(|name| properties.get(name.as_str()).is_none())
+        .cloned()
+        .collect();
+    if parameter_names.is_empty() {
+        return Ok(());
+    }
+    Err(FunctionCallParsingError::UnknownParameters {
+        function_name: function_call.function_name.clone(),
+        parameter_names,
+    })
+}
+
+/// Parses a tool call where the function name is in the field `name_field` and the parameters
+/// are in the field `parameters_field`. The id of the call is taken from the `id` field if present.
+fn into_function_call_from_fields(
+    input: Value,
+    name_field: &str,
+    parameters_field: &str,
+) -> Result<FunctionCallArgs, FunctionCallParsingError> {
+    let name = match input.get(name_field) {
         Some(name) => name,
         None => {
-            return Err(FunctionCallParsingError::Parsing {
-                issue: format!(
-                    "The tool call `function_name` field is not a string in:\n{input}"
-                ),
+            return Err(FunctionCallParsingError::MissingFunctionName {
+                field: name_field.to_owned(),
+                call: input.to_string(),
             });
         }
     };
-    let parameters = input.get("parameters");
+    if !name.is_string() {
+        return Err(FunctionCallParsingError::FunctionNameNotString {
+            field: name_field.to_owned(),
+            call: input.to_string(),
+        });
+    }
+    // the id is only metadata, so a numeric id is stringified and any other id is ignored rather
+    // than failing a call that is otherwise valid
+    let id = match input.get("id") {
+        Some(Value::String(id)) => Some(id.clone()),
+        Some(Value::Number(id)) => Some(id.to_string()),
+        _ => None,
+    };
+    let parameters = input.get(parameters_field);
     let Some(parameters) = parameters else {
-        return Err(FunctionCallParsingError::Parsing {
-            issue: format!("The tool call is missing the `parameters` field in:\n{input}"),
+        return Err(FunctionCallParsingError::MissingParameters {
+            field: parameters_field.to_owned(),
+            call: input.to_string(),
         });
     };
     if !parameters.is_object() {
-        return Err(FunctionCallParsingError::Parsing {
-            issue: format!("The tool call `parameters` field is not an object in:\n{input}"),
+        return Err(FunctionCallParsingError::ParametersNotObject {
+            field: parameters_field.to_owned(),
+            call: input.to_string(),
         });
     }
     let mut map = unwrap_match!(input, Value::Object);
-    let name = map.remove("function_name").unwrap();
+    let name = map.remove(name_field).unwrap();
     let name = unwrap_match!(name, Value::String);
-    let parameters = map.remove("parameters").unwrap();
+    let parameters = map.remove(parameters_field).unwrap();
     let parameters = unwrap_match!(parameters, Value::Object);
-    return Ok(FunctionCallArgs { function_name: name, parameters });
+    Ok(FunctionCallArgs { function_name: name, parameters, id })
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct FunctionCallArgs {
-    function_name: String,
-    parameters: Map<String, Value>,
+    pub(crate) function_name: String,
+    pub(crate) parameters: Map<String, Value>,
+    /// The id the provider gave the tool call, used to correlate the result with the call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+}
+
+impl FunctionCallArgs {
+    /// A call of `function_name` with `parameters`, e.g. for a call parsed elsewhere.
+    pub fn new(function_name: String, parameters: Map<String, Value>) -> Self {
+        Self {
+            function_name,
+            parameters,
+            id: None,
+        }
+    }
+
+    /// The name of the function to call.
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+
+    /// The parameters to call the function with.
+    pub fn parameters(&self) -> &Map<String, Value> {
+        &self.parameters
+    }
+
+    /// The id the provider gave the tool call, if any.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
 }
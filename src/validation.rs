@@ -0,0 +1,101 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use serde_json::{Map, Value};
+
+use crate::{providers, FunctionCallArgs, FunctionCallParsingError};
+
+/// The compiled parameter validator of each function of a toolbox. The entry for each function is
+/// created when the tool is added, and the validator is compiled on the first validated call of
+/// the function, so later calls do not search the schema or compile it again.
+#[derive(Default)]
+pub(crate) struct ValidatorCache {
+    functions: HashMap<&'static str, OnceLock<Result<jsonschema::Validator, String>>>,
+}
+
+impl ValidatorCache {
+    pub(crate) fn add(&mut self, function_names: &[&'static str]) {
+        for function_name in function_names {
+            self.functions.insert(function_name, OnceLock::new());
+        }
+    }
+
+    pub(crate) fn remove(&mut self, function_names: &[&'static str]) {
+        for function_name in function_names {
+            self.functions.remove(function_name);
+        }
+    }
+
+    /// Validates the parameters of `function_call` against the parameter schema of the function in
+    /// `tool_schema`, the schema of the tool that has the function. Does nothing if the function
+    /// is not in this cache.
+    pub(crate) fn validate(
+        &self,
+        tool_schema: &Map<String, Value>,
+        function_call: &FunctionCallArgs,
+    ) -> Result<(), FunctionCallParsingError> {
+        let Some(validator) = self.functions.get(function_call.function_name.as_str()) else {
+            return Ok(());
+        };
+        let validator = validator
+            .get_or_init(|| compile(tool_schema, &function_call.function_name))
+            .as_ref()
+            .map_err(|issue| FunctionCallParsingError::Parsing { issue: issue.clone() })?;
+        let parameters = Value::Object(function_call.parameters.clone());
+        validator
+            .validate(&parameters)
+            .map_err(|error| FunctionCallParsingError::SchemaViolation {
+                path: error.instance_path.to_string(),
+                expected: error.to_string(),
+            })
+    }
+}
+
+/// Compiles the parameter schema of the function `function_name` in `tool_schema`. A function
+/// missing from `tool_schema` has no parameter schema, so accepts any parameters.
+fn compile(tool_schema: &Map<String, Value>, function_name: &str) -> Result<jsonschema::Validator, String> {
+    let parameters_schema = providers::function_schemas(tool_schema)
+        .find(|function_schema| function_schema["properties"]["function_name"]["const"] == function_name)
+        .map(providers::parameters_schema)
+        .unwrap_or(Value::Bool(true));
+    jsonschema::draft7::new(&parameters_schema)
+        .map_err(|error| format!("The schema for `{function_name}` is not valid:\n{error}"))
+}
+
+/// Checks that `schema`, e.g. a toolbox schema, is a valid draft-07 JSON Schema and that both it
+/// and the parameter schema of each function compile, so `$ref`s resolve. Meant for a unit test that
+/// catches a malformed tool schema before an llm silently fails to follow it.
+pub fn assert_valid_schema(schema: &Value) -> Result<(), Vec<FunctionCallParsingError>> {
+    let mut errors: Vec<FunctionCallParsingError> = jsonschema::draft7::meta::VALIDATOR
+        .iter_errors(schema)
+        .map(|error| FunctionCallParsingError::SchemaViolation {
+            path: error.instance_path.to_string(),
+            expected: error.to_string(),
+        })
+        .collect();
+    if let Value::Object(tool_schema) = schema {
+        for function_schema in providers::function_schemas(tool_schema) {
+            let parameters_schema = providers::parameters_schema(function_schema);
+            if let Err(error) = jsonschema::draft7::new(&parameters_schema) {
+                errors.push(FunctionCallParsingError::Parsing {
+                    issue: format!(
+                        "The schema for `{}` is not valid:\n{error}",
+                        function_schema["properties"]["function_name"]["const"].as_str().unwrap_or_default()
+                    ),
+                });
+            }
+        }
+    }
+    // e.g. the `$ref`s of a parameter schema that only resolve while it is the root
+    if errors.is_empty() {
+        if let Err(error) = jsonschema::draft7::new(schema) {
+            errors.push(FunctionCallParsingError::Parsing {
+                issue: format!("The schema is not valid:\n{error}"),
+            });
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
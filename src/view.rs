@@ -0,0 +1,241 @@
+use serde_json::{Map, Value};
+
+use crate::{providers, FunctionCallArgs, FunctionCallError, ToolBox, ToolBoxLocal};
+
+/// A view of a [ToolBoxLocal] with parameters bound to fixed values, see
+/// [ToolBoxLocal::bind_parameter].
+pub struct ToolBoxLocalView<'a, O, E> {
+    toolbox: &'a ToolBoxLocal<O, E>,
+    /// parameters injected into every call of a function that takes them
+    bound_parameters: Map<String, Value>,
+}
+
+impl<'a, O, E> ToolBoxLocalView<'a, O, E> {
+    pub(crate) fn new(toolbox: &'a ToolBoxLocal<O, E>) -> Self {
+        Self {
+            toolbox,
+            bound_parameters: Map::new(),
+        }
+    }
+
+    /// Also binds the parameter `name` to `value`.
+    pub fn bind_parameter(mut self, name: &str, value: Value) -> Self {
+        self.bound_parameters.insert(name.to_owned(), value);
+        self
+    }
+
+    /// Calls the tool with the given name and parameters.
+    pub async fn call_from_value(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.toolbox.into_function_call_from_value(function_call)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool with the bound parameters injected, replacing any value the llm provided.
+    pub async fn call_from_args(&self, mut function_call: FunctionCallArgs) -> Result<Result<O, E>, FunctionCallError> {
+        bind_parameters(self.toolbox.schema(), &self.bound_parameters, &mut function_call);
+        self.toolbox.call_from_args(function_call).await
+    }
+
+    /// The schema of the toolbox without the bound parameters, see [ToolBoxLocal::schema].
+    pub fn schema(&self) -> Map<String, Value> {
+        let mut schema = self.toolbox.schema().clone();
+        if let Some(Value::Array(function_schemas)) = schema.get_mut("oneOf") {
+            for function_schema in function_schemas.iter_mut() {
+                *function_schema = hide_parameters(function_schema.take(), &self.bound_parameters);
+            }
+        }
+        schema
+    }
+
+    /// The contract snapshot of the toolbox without the bound parameters, see
+    /// [ToolBoxLocal::contract_snapshot].
+    pub fn contract_snapshot(&self) -> Value {
+        providers::contract_snapshot(
+            self.provider_function_schemas()
+                .map(|function_schema| providers::contract_function_schema(&function_schema)),
+        )
+    }
+
+    /// The OpenAI schema of the toolbox without the bound parameters, see [ToolBoxLocal::openai_schema].
+    pub fn openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The strict OpenAI schema of the toolbox without the bound parameters, see
+    /// [ToolBoxLocal::strict_openai_schema].
+    pub fn strict_openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::strict_openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The Anthropic schema of the toolbox without the bound parameters, see
+    /// [ToolBoxLocal::anthropic_schema].
+    pub fn anthropic_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::anthropic_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The Gemini schema of the toolbox without the bound parameters, see [ToolBoxLocal::gemini_schema].
+    pub fn gemini_schema(&self) -> Value {
+        let function_declarations = self
+            .provider_function_schemas()
+            .map(|function_schema| providers::gemini_function_schema(&function_schema))
+            .collect();
+        serde_json::json!({ "functionDeclarations": Value::Array(function_declarations) })
+    }
+
+    /// The MCP `tools/list` entries of the toolbox without the bound parameters, see
+    /// [ToolBoxLocal::mcp_tool_list].
+    #[cfg(feature = "mcp")]
+    pub fn mcp_tool_list(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::mcp_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The `oneOf` entries every provider schema is built from, without the bound parameters
+    fn provider_function_schemas(&self) -> impl Iterator<Item = Value> + '_ {
+        self.toolbox
+            .provider_function_schemas()
+            .map(|function_schema| hide_parameters(function_schema, &self.bound_parameters))
+    }
+}
+
+/// A view of a [ToolBox] with parameters bound to fixed values, see [ToolBox::bind_parameter].
+pub struct ToolBoxView<'a, O, E> {
+    toolbox: &'a ToolBox<O, E>,
+    /// parameters injected into every call of a function that takes them
+    bound_parameters: Map<String, Value>,
+}
+
+impl<'a, O, E> ToolBoxView<'a, O, E> {
+    pub(crate) fn new(toolbox: &'a ToolBox<O, E>) -> Self {
+        Self {
+            toolbox,
+            bound_parameters: Map::new(),
+        }
+    }
+
+    /// Also binds the parameter `name` to `value`.
+    pub fn bind_parameter(mut self, name: &str, value: Value) -> Self {
+        self.bound_parameters.insert(name.to_owned(), value);
+        self
+    }
+
+    /// Calls the tool with the given name and parameters.
+    pub async fn call_from_value(&self, function_call: Value) -> Result<Result<O, E>, FunctionCallError> {
+        let function_call = self.toolbox.into_function_call_from_value(function_call)?;
+        self.call_from_args(function_call).await
+    }
+
+    /// Calls the tool with the bound parameters injected, replacing any value the llm provided.
+    pub async fn call_from_args(&self, mut function_call: FunctionCallArgs) -> Result<Result<O, E>, FunctionCallError> {
+        bind_parameters(self.toolbox.schema(), &self.bound_parameters, &mut function_call);
+        self.toolbox.call_from_args(function_call).await
+    }
+
+    /// The schema of the toolbox without the bound parameters, see [ToolBox::schema].
+    pub fn schema(&self) -> Map<String, Value> {
+        let mut schema = self.toolbox.schema().clone();
+        if let Some(Value::Array(function_schemas)) = schema.get_mut("oneOf") {
+            for function_schema in function_schemas.iter_mut() {
+                *function_schema = hide_parameters(function_schema.take(), &self.bound_parameters);
+            }
+        }
+        schema
+    }
+
+    /// The contract snapshot of the toolbox without the bound parameters, see
+    /// [ToolBox::contract_snapshot].
+    pub fn contract_snapshot(&self) -> Value {
+        providers::contract_snapshot(
+            self.provider_function_schemas()
+                .map(|function_schema| providers::contract_function_schema(&function_schema)),
+        )
+    }
+
+    /// The OpenAI schema of the toolbox without the bound parameters, see [ToolBox::openai_schema].
+    pub fn openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The strict OpenAI schema of the toolbox without the bound parameters, see
+    /// [ToolBox::strict_openai_schema].
+    pub fn strict_openai_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::strict_openai_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The Anthropic schema of the toolbox without the bound parameters, see
+    /// [ToolBox::anthropic_schema].
+    pub fn anthropic_schema(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::anthropic_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The Gemini schema of the toolbox without the bound parameters, see [ToolBox::gemini_schema].
+    pub fn gemini_schema(&self) -> Value {
+        let function_declarations = self
+            .provider_function_schemas()
+            .map(|function_schema| providers::gemini_function_schema(&function_schema))
+            .collect();
+        serde_json::json!({ "functionDeclarations": Value::Array(function_declarations) })
+    }
+
+    /// The MCP `tools/list` entries of the toolbox without the bound parameters, see
+    /// [ToolBox::mcp_tool_list].
+    #[cfg(feature = "mcp")]
+    pub fn mcp_tool_list(&self) -> Vec<Value> {
+        self.provider_function_schemas()
+            .map(|function_schema| providers::mcp_function_schema(&function_schema))
+            .collect()
+    }
+
+    /// The `oneOf` entries every provider schema is built from, without the bound parameters
+    fn provider_function_schemas(&self) -> impl Iterator<Item = Value> + '_ {
+        self.toolbox
+            .provider_function_schemas()
+            .map(|function_schema| hide_parameters(function_schema, &self.bound_parameters))
+    }
+}
+
+//************************************************************************//
+
+/// Inserts the bound parameters the function takes into the parameters of `function_call`
+fn bind_parameters(
+    schema: &Map<String, Value>,
+    bound_parameters: &Map<String, Value>,
+    function_call: &mut FunctionCallArgs,
+) {
+    let Some(function_schema) = providers::function_schemas(schema).find(|function_schema| {
+        function_schema["properties"]["function_name"]["const"] == function_call.function_name
+    }) else {
+        return;
+    };
+    let properties = &function_schema["properties"]["parameters"]["properties"];
+    for (name, value) in bound_parameters {
+        if properties.get(name).is_some() {
+            function_call.parameters.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Removes the bound parameters from the properties and required list of a `oneOf` entry
+fn hide_parameters(mut function_schema: Value, bound_parameters: &Map<String, Value>) -> Value {
+    let parameters = &mut function_schema["properties"]["parameters"];
+    if let Some(Value::Object(properties)) = parameters.get_mut("properties") {
+        properties.retain(|name, _| !bound_parameters.contains_key(name));
+    }
+    if let Some(Value::Array(required)) = parameters.get_mut("required") {
+        required.retain(|name| !name.as_str().is_some_and(|name| bound_parameters.contains_key(name)));
+    }
+    function_schema
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 pub mod toolbox_by_hand {
-    use std::{any::Any, cell::LazyCell, convert::Infallible, fmt::Display};
+    use std::{any::Any, convert::Infallible, fmt::Display, sync::LazyLock};
 
     use llmtoolbox::{FunctionCallError, Tool, ToolBoxLocal};
     use serde_json::{json, Map, Value};
@@ -29,8 +29,8 @@ pub mod toolbox_by_hand {
 
     //************************************************************************//
 
-    const _MYTOOL_SCHEMA: LazyCell<&'static serde_json::Value> = LazyCell::new(|| {
-        Box::leak(Box::new(json!(
+    static _MYTOOL_SCHEMA: LazyLock<serde_json::Value> = LazyLock::new(|| {
+        json!(
         {
             "$schema": "http://json-schema.org/draft-07/schema#",
             "oneOf": [
@@ -56,10 +56,10 @@ pub mod toolbox_by_hand {
                 }
             ]
         }
-        )))
+        )
     });
 
-    const _MYTOOL_GREETING_PARAMETERS_SCHEMA: LazyCell<serde_json::Value> = LazyCell::new(|| {
+    static _MYTOOL_GREETING_PARAMETERS_SCHEMA: LazyLock<serde_json::Value> = LazyLock::new(|| {
         json!(
             {
                 "type": "object",
@@ -74,7 +74,7 @@ pub mod toolbox_by_hand {
         )
     });
 
-    const _MYTOOL_GOODBYE_PARAMETERS_SCHEMA: LazyCell<serde_json::Value> = LazyCell::new(|| {
+    static _MYTOOL_GOODBYE_PARAMETERS_SCHEMA: LazyLock<serde_json::Value> = LazyLock::new(|| {
         json!(
             {
                 "type": "object",
@@ -245,6 +245,348 @@ pub mod toolbox_different_regular_return_type {
         let _schema = serde_json::to_string_pretty(&schema).unwrap();
     }
 
+    #[test]
+    fn openai_schema() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let openai_schema = toolbox.openai_schema();
+        assert_eq!(openai_schema.len(), 2);
+        assert_eq!(openai_schema[0]["type"], "function");
+        assert_eq!(openai_schema[0]["function"]["name"], "greet");
+        assert_eq!(
+            openai_schema[0]["function"]["parameters"]["properties"]["greeting"]["type"],
+            "string"
+        );
+        assert_eq!(openai_schema[1]["function"]["name"], "talk");
+    }
+
+    #[test]
+    fn description_wrapper() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new().with_description_wrapper("Be concise.", "Answer in English.");
+        toolbox.add_tool(MyTool::new()).unwrap();
+        for function in toolbox.openai_schema() {
+            let description = function["function"]["description"].as_str().unwrap();
+            assert!(description.starts_with("Be concise.\n"));
+            assert!(description.ends_with("\nAnswer in English."));
+        }
+        let stored_description = toolbox.schema()["oneOf"][0]["description"].as_str().unwrap();
+        assert!(!stored_description.starts_with("Be concise."));
+    }
+
+    #[test]
+    fn anthropic_schema() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let anthropic_schema = toolbox.anthropic_schema();
+        assert_eq!(anthropic_schema.len(), 2);
+        assert_eq!(anthropic_schema[0]["name"], "greet");
+        assert_eq!(
+            anthropic_schema[0]["input_schema"]["properties"]["greeting"]["type"],
+            "string"
+        );
+        assert_eq!(anthropic_schema[1]["name"], "talk");
+    }
+
+    #[test]
+    fn builder() {
+        let toolbox = llmtoolbox::ToolBox::<Box<dyn std::any::Any>, std::convert::Infallible>::builder()
+            .add_tool(MyTool::new())
+            .build();
+        let Ok(toolbox) = toolbox else {
+            panic!("The tool should not collide");
+        };
+        assert!(toolbox.function_exists("greet"));
+        let toolbox = llmtoolbox::ToolBox::<Box<dyn std::any::Any>, std::convert::Infallible>::builder()
+            .add_tool(MyTool::new())
+            .add_tool(MyTool::new())
+            .build();
+        assert!(toolbox.is_err());
+    }
+
+    #[test]
+    fn default() {
+        #[derive(Default)]
+        struct Agent {
+            toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible>,
+            local_toolbox: llmtoolbox::ToolBoxLocal<Box<dyn std::any::Any>, std::convert::Infallible>,
+        }
+        let mut agent = Agent::default();
+        assert!(agent.toolbox.is_empty());
+        assert!(agent.local_toolbox.is_empty());
+        agent.toolbox.add_tool(MyTool::new()).unwrap();
+        assert!(agent.toolbox.function_exists("greet"));
+    }
+
+    #[test]
+    fn from_tools() {
+        type Output = Box<dyn std::any::Any>;
+        type Boxed = Box<dyn llmtoolbox::Tool<Output, std::convert::Infallible> + Send + Sync>;
+        let toolbox = llmtoolbox::ToolBox::from_tools([Box::new(MyTool::new()) as Boxed]).unwrap();
+        assert!(toolbox.function_exists("greet"));
+        let error = llmtoolbox::ToolBox::from_tools([Box::new(MyTool::new()) as Boxed, Box::new(MyTool::new())])
+            .err()
+            .unwrap();
+        assert_eq!(error.conflicting_name, "greet");
+    }
+
+    #[tokio::test]
+    async fn call_from_anthropic() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_use = serde_json::json!({
+            "type": "tool_use",
+            "id": "toolu_01A09q90qw90lq917835lq9",
+            "name": "greet",
+            "input": {
+                "greeting": "This is a greeting"
+            }
+        });
+        let message = match toolbox.call_from_anthropic(tool_use).await {
+            Ok(Ok(tool_result)) => tool_result,
+            Err(error) => panic!("{error}"),
+        };
+        assert_eq!(
+            *message.downcast::<String>().unwrap(),
+            "This is the greeting `This is a greeting`".to_owned()
+        );
+    }
+
+    #[tokio::test]
+    async fn call_with_feedback() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let mut errors = Vec::new();
+        let output = toolbox
+            .call_with_feedback(serde_json::json!({"function_name": "greet", "parameters": {}}), 2, |error| {
+                errors.push(error.to_string());
+                serde_json::json!({"function_name": "greet", "parameters": {"greeting": "Hi"}})
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*output.downcast::<String>().unwrap(), "This is the greeting `Hi`");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Missing `greeting`"));
+
+        let mut retries = 0;
+        let result = toolbox
+            .call_with_feedback(serde_json::json!({"function_name": "greets", "parameters": {}}), 2, |_| {
+                retries += 1;
+                serde_json::json!({"function_name": "greets", "parameters": {}})
+            })
+            .await;
+        assert!(matches!(result, Err(llmtoolbox::FunctionCallError::FunctionNotFound { .. })));
+        assert_eq!(retries, 2);
+    }
+
+    #[tokio::test]
+    async fn error_kind() {
+        use llmtoolbox::FunctionCallErrorKind;
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let error = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greets", "parameters": {}}))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), FunctionCallErrorKind::FunctionNotFound);
+        assert!(!error.kind().is_retryable());
+        let error = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greet", "parameters": {}}))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), FunctionCallErrorKind::Parsing);
+        assert!(error.kind().is_retryable());
+        let error = toolbox.into_function_call_from_str("{").unwrap_err();
+        assert_eq!(error.kind(), FunctionCallErrorKind::Parsing);
+    }
+
+    #[test]
+    fn malformed_call_parts() {
+        use llmtoolbox::FunctionCallParsingError;
+        let error = llmtoolbox::parse_function_call(serde_json::json!({"parameters": {}})).unwrap_err();
+        assert!(matches!(&error, FunctionCallParsingError::MissingFunctionName { field, .. } if field == "function_name"));
+        assert!(error.to_string().starts_with("The tool call is missing the `function_name` field in:"));
+        let error = llmtoolbox::parse_function_call(serde_json::json!({"function_name": 1, "parameters": {}})).unwrap_err();
+        assert!(matches!(error, FunctionCallParsingError::FunctionNameNotString { .. }));
+        let error = llmtoolbox::parse_function_call(serde_json::json!({"function_name": "greet"})).unwrap_err();
+        assert!(matches!(&error, FunctionCallParsingError::MissingParameters { field, .. } if field == "parameters"));
+        let error = llmtoolbox::parse_function_call(serde_json::json!({"function_name": "greet", "parameters": "{}"})).unwrap_err();
+        assert!(matches!(&error, FunctionCallParsingError::ParametersNotObject { call, .. } if call.contains("greet")));
+        assert!(error.to_string().starts_with("The tool call `parameters` field is not an object in:"));
+        assert!(error.kind().is_retryable());
+        // the fields are named after the provider's format
+        let error = llmtoolbox::ToolBox::<Box<dyn std::any::Any>, std::convert::Infallible>::new()
+            .into_function_call_from_anthropic(serde_json::json!({"type": "tool_use", "name": "greet"}))
+            .unwrap_err();
+        assert!(matches!(&error, FunctionCallParsingError::MissingParameters { field, .. } if field == "input"));
+    }
+
+    #[tokio::test]
+    async fn call_from_openai() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_call = serde_json::json!({
+            "id": "call_abc123",
+            "type": "function",
+            "function": {
+                "name": "greet",
+                "arguments": "{\"greeting\": \"This is a greeting\"}"
+            }
+        });
+        let function_call = toolbox.into_function_call_from_openai(tool_call.clone()).unwrap();
+        assert_eq!(function_call.id(), Some("call_abc123"));
+        let message = match toolbox.call_from_openai(tool_call).await {
+            Ok(Ok(tool_result)) => tool_result,
+            Err(error) => panic!("{error}"),
+        };
+        assert_eq!(
+            *message.downcast::<String>().unwrap(),
+            "This is the greeting `This is a greeting`".to_owned()
+        );
+        let truncated = serde_json::json!({
+            "type": "function",
+            "function": { "name": "greet", "arguments": "{\"greeting\": \"This is" }
+        });
+        let error = toolbox.into_function_call_from_openai(truncated).unwrap_err();
+        assert!(error.to_string().contains("truncated"));
+    }
+
+    #[test]
+    fn function_call_id() {
+        let toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        let function_call = toolbox
+            .into_function_call_from_value(serde_json::json!({
+                "id": "call_abc123",
+                "function_name": "greet",
+                "parameters": {}
+            }))
+            .unwrap();
+        assert_eq!(function_call.id(), Some("call_abc123"));
+        let function_call = toolbox
+            .into_function_call_from_value(serde_json::json!({
+                "function_name": "greet",
+                "parameters": {}
+            }))
+            .unwrap();
+        assert_eq!(function_call.id(), None);
+    }
+
+    #[test]
+    fn invalid_json_keeps_the_serde_error() {
+        let toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        let issue = |input: &str| match toolbox.into_function_call_from_str(input) {
+            Err(llmtoolbox::FunctionCallParsingError::Parsing { issue }) => issue,
+            _ => panic!("expected a parsing error"),
+        };
+        let truncated = issue(r#"{"function_name": "greet", "parame"#);
+        assert!(truncated.contains("truncated"), "{truncated}");
+        assert!(truncated.contains("line 1 column 34"), "{truncated}");
+        let malformed = issue(r#"{"function_name": greet}"#);
+        assert!(malformed.starts_with("The tool call is not valid json"), "{malformed}");
+        assert!(malformed.contains("line 1 column 19"), "{malformed}");
+    }
+
+    #[test]
+    fn parse_without_toolbox() {
+        let function_call = llmtoolbox::parse_function_call_str(
+            r#"{"function_name": "greet", "parameters": {"greeting": "Hi"}, "id": "call_1"}"#,
+        )
+        .unwrap();
+        assert_eq!(function_call.function_name(), "greet");
+        assert_eq!(function_call.parameters()["greeting"], "Hi");
+        assert_eq!(function_call.id(), Some("call_1"));
+        // the function does not need to exist
+        let function_call = llmtoolbox::parse_function_call(
+            serde_json::json!({"function_name": "unknown", "parameters": {}}),
+        )
+        .unwrap();
+        assert_eq!(function_call.function_name(), "unknown");
+        assert!(llmtoolbox::parse_function_call(serde_json::json!({"parameters": {}})).is_err());
+    }
+
+    #[test]
+    fn function_call_args_round_trip() {
+        let toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        let value = serde_json::json!({
+            "function_name": "greet",
+            "parameters": {"greeting": "hi"},
+            "id": "call_abc123"
+        });
+        let function_call = toolbox.into_function_call_from_value(value.clone()).unwrap();
+        let serialized = serde_json::to_value(&function_call).unwrap();
+        assert_eq!(serialized, value);
+        let deserialized: llmtoolbox::FunctionCallArgs = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, function_call);
+        let without_id = llmtoolbox::FunctionCallArgs::new("greet".to_owned(), serde_json::Map::new());
+        assert_eq!(
+            serde_json::to_value(&without_id).unwrap(),
+            serde_json::json!({"function_name": "greet", "parameters": {}})
+        );
+    }
+
+    #[tokio::test]
+    async fn non_string_id() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let function_call = toolbox
+            .into_function_call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"greeting": "hi"}, "id": 7}))
+            .unwrap();
+        assert_eq!(function_call.id(), Some("7"));
+        let function_call = toolbox
+            .into_function_call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"greeting": "hi"}, "id": null}))
+            .unwrap();
+        assert_eq!(function_call.id(), None);
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"greeting": "hi"}, "id": 7}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*output.downcast::<String>().unwrap(), "This is the greeting `hi`");
+    }
+
+    #[tokio::test]
+    async fn function_call_args_new() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let mut parameters = serde_json::Map::new();
+        parameters.insert("greeting".to_owned(), serde_json::json!("hi"));
+        let function_call = llmtoolbox::FunctionCallArgs::new("greet".to_owned(), parameters);
+        assert_eq!(function_call.function_name(), "greet");
+        assert_eq!(function_call.parameters()["greeting"], "hi");
+        assert_eq!(function_call.id(), None);
+        let output = toolbox.call_from_args(function_call).await.unwrap().unwrap();
+        assert_eq!(*output.downcast::<String>().unwrap(), "This is the greeting `hi`");
+    }
+
+    #[test]
+    fn merge() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        let mut other = llmtoolbox::ToolBox::new();
+        other.add_tool(MyTool::new()).unwrap();
+        toolbox.merge(other).ok().unwrap();
+        assert!(toolbox.function_exists("greet"));
+        let mut other = llmtoolbox::ToolBox::new();
+        other.add_tool(MyTool::new()).unwrap();
+        let error = toolbox.merge(other).unwrap_err();
+        assert_eq!(error.conflicting_name, "greet");
+        assert!(error.tool.function_exists("greet"));
+    }
+
     fn assert_send_sync<T: Send + Sync>() {}
 
     #[tokio::test]
@@ -350,6 +692,73 @@ pub mod toolbox_same_regular_return_type {
             Err(error) => panic!("{error}"),
         };
     }
+
+    #[test]
+    fn blocking_call() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_call_value = serde_json::json!({
+            "function_name": "greet",
+            "parameters": {
+                "greeting": "hi"
+            }
+        });
+        match toolbox.call_from_value_blocking(tool_call_value) {
+            Ok(Ok(tool_result)) => assert_eq!(tool_result, "This is the greeting `hi`"),
+            Err(error) => panic!("{error}"),
+        };
+    }
+
+    #[test]
+    fn function_lookup() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        assert!(toolbox.function_exists("greet"));
+        assert!(!toolbox.function_exists("Greet"));
+        assert!(!toolbox.function_exists("goodbye"));
+        assert_eq!(toolbox.find_function_case_insensitive("GREET"), Some("greet"));
+        assert_eq!(toolbox.find_function_case_insensitive("goodbye"), None);
+    }
+
+    #[test]
+    fn suggests_a_similar_function() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let error = toolbox
+            .call_from_value_blocking(serde_json::json!({"function_name": "gret", "parameters": {}}))
+            .unwrap_err();
+        match &error {
+            llmtoolbox::FunctionCallError::FunctionNotFound { did_you_mean, .. } => {
+                assert_eq!(did_you_mean.as_deref(), Some("greet"))
+            }
+            _ => panic!("expected the function to not be found"),
+        }
+        assert!(error.to_string().ends_with(", did you mean `greet`?"));
+        let error = toolbox
+            .call_from_value_blocking(serde_json::json!({"function_name": "weather", "parameters": {}}))
+            .unwrap_err();
+        assert!(matches!(error, llmtoolbox::FunctionCallError::FunctionNotFound { did_you_mean: None, .. }));
+    }
+
+    #[test]
+    fn parsing_errors_name_the_function() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let issue = |function_call: serde_json::Value| match toolbox.call_from_value_blocking(function_call) {
+            Err(llmtoolbox::FunctionCallError::Parsing { issue }) => issue,
+            _ => panic!("expected a parsing error"),
+        };
+        assert_eq!(
+            issue(serde_json::json!({"function_name": "greet", "parameters": {"greeting": 1}})),
+            "Parameter `greeting` of function `greet` does not follow schema: invalid type: integer `1`, expected a string"
+        );
+        assert!(issue(serde_json::json!({"function_name": "greet", "parameters": {}}))
+            .starts_with("Missing `greeting` parameter of function `greet`"));
+    }
 }
 
 #[cfg(test)]
@@ -583,3 +992,3086 @@ pub mod generics {
         let _schema = serde_json::to_string_pretty(&schema).unwrap();
     }
 }
+
+#[cfg(test)]
+pub mod cfg_gated_tool_part {
+    use llmtoolbox::Tool;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// This
+        /// `greeting` - descr
+        #[tool_part]
+        #[cfg(test)]
+        fn greet(&self, greeting: &str) -> String {
+            format!("This is the greeting `{greeting}`")
+        }
+
+        /// Only exists outside of tests
+        /// `farewell` - descr
+        #[tool_part]
+        #[cfg(not(test))]
+        fn goodbye(&self, farewell: &str) -> String {
+            format!("This is the farewell `{farewell}`")
+        }
+    }
+
+    #[tokio::test]
+    async fn only_active_functions_are_included() {
+        let tool = MyTool::new();
+        let function_names = <MyTool as Tool<String, std::convert::Infallible>>::function_names(&tool);
+        assert_eq!(function_names, &["greet"]);
+        let schema = <MyTool as Tool<String, std::convert::Infallible>>::schema(&tool);
+        assert_eq!(schema["oneOf"].as_array().unwrap().len(), 1);
+
+        let mut toolbox: llmtoolbox::ToolBoxLocal<String, std::convert::Infallible> =
+            llmtoolbox::ToolBoxLocal::new();
+        toolbox.add_tool(tool).unwrap();
+        let tool_call_value = serde_json::json!({
+            "function_name": "goodbye",
+            "parameters": {
+                "farewell": "This is a farewell"
+            }
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Err(llmtoolbox::FunctionCallError::FunctionNotFound { function_name, .. }) => {
+                assert_eq!(function_name, "goodbye")
+            }
+            _ => panic!("`goodbye` should not be callable"),
+        };
+    }
+
+    #[derive(Debug)]
+    struct FeatureTool;
+
+    #[llmtool::tool]
+    impl FeatureTool {
+        /// Greets
+        /// `greeting` - The greeting
+        #[tool_part]
+        fn greet(&self, greeting: String) -> String {
+            format!("This is the greeting `{greeting}`")
+        }
+
+        /// Counts, only with the `validation` feature
+        #[tool_part]
+        #[cfg(feature = "validation")]
+        fn count(&self) -> u32 {
+            3
+        }
+    }
+
+    #[cfg(feature = "validation")]
+    #[tokio::test]
+    async fn feature_gated_part_with_feature() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(FeatureTool).unwrap();
+        assert_eq!(toolbox.schema()["oneOf"].as_array().unwrap().len(), 2);
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "count", "parameters": {}}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*output.downcast::<u32>().unwrap(), 3);
+    }
+
+    #[cfg(not(feature = "validation"))]
+    #[tokio::test]
+    async fn feature_gated_part_without_feature() {
+        // `count` is gated out, so `String` is the common output
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(FeatureTool).unwrap();
+        assert_eq!(toolbox.schema()["oneOf"].as_array().unwrap().len(), 1);
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"greeting": "Hi"}}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output, "This is the greeting `Hi`");
+    }
+}
+
+#[cfg(test)]
+pub mod json_output {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool(json_output)]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// This
+        /// `greeting` - descr
+        #[tool_part]
+        fn greet(&self, greeting: &str) -> String {
+            format!("This is the greeting `{greeting}`")
+        }
+
+        /// func descrip
+        /// `topic` - field description
+        #[tool_part]
+        async fn talk(&self, topic: ConverstationTopic) -> ConverstationTopic {
+            topic
+        }
+    }
+
+    /// Description
+    #[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+    pub struct ConverstationTopic {
+        pub topic: String,
+        pub opinion: String,
+    }
+
+    #[tokio::test]
+    async fn output_is_serialized() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_call_value = serde_json::json!({
+            "function_name": "talk",
+            "parameters": {
+                "topic": {
+                    "topic": "rust",
+                    "opinion": "good"
+                }
+            }
+        });
+        let function_call = toolbox.into_function_call_from_value(tool_call_value).unwrap();
+        match toolbox.call_to_json(function_call).await {
+            Ok(Ok(json)) => assert_eq!(
+                json,
+                serde_json::json!({
+                    "topic": "rust",
+                    "opinion": "good"
+                })
+            ),
+            Err(error) => panic!("{error}"),
+        };
+    }
+
+    #[tokio::test]
+    async fn hooks_see_json_calls() {
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        toolbox.set_on_call({
+            let calls = calls.clone();
+            move |function_name, _parameters| calls.lock().unwrap().push(function_name.to_owned())
+        });
+        let function_call = toolbox
+            .into_function_call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"greeting": "Hi"}}))
+            .unwrap();
+        let json = toolbox.call_to_json(function_call).await.unwrap().unwrap();
+        assert_eq!(json, "This is the greeting `Hi`");
+        assert_eq!(*calls.lock().unwrap(), vec!["greet".to_owned()]);
+        #[cfg(feature = "stats")]
+        assert_eq!(toolbox.stats()["greet"].count, 1);
+    }
+}
+
+#[cfg(test)]
+pub mod tool_metadata {
+    use llmtoolbox::Tool;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    /// Greets people.
+    /// Always polite.
+    #[llmtool::tool(group = "greetings")]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// This
+        /// `greeting` - descr
+        #[tool_part(returns = "The formatted greeting")]
+        fn greet(&self, greeting: &str) -> String {
+            format!("This is the greeting `{greeting}`")
+        }
+
+        /// Goodbye
+        #[tool_part]
+        fn goodbye(&self) -> String {
+            "Goodbye!".to_owned()
+        }
+    }
+
+    #[test]
+    fn returns_is_in_schema() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        assert_eq!(
+            MyToolTrait::returns_description(&tool, "greet"),
+            Some("The formatted greeting")
+        );
+        assert_eq!(MyToolTrait::returns_description(&tool, "goodbye"), None);
+        let schema = MyToolTrait::schema(&tool);
+        let one_of = schema["oneOf"].as_array().unwrap();
+        assert_eq!(one_of[0]["returns"], "The formatted greeting");
+        assert!(one_of[1].get("returns").is_none());
+    }
+
+    #[test]
+    fn description_is_the_doc_comment() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        let schema = MyToolTrait::schema(&tool);
+        assert_eq!(schema["oneOf"][1]["description"], "Goodbye");
+    }
+
+    #[test]
+    fn descriptions_follow_function_names() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        assert_eq!(MyToolTrait::function_names(&tool), &["greet", "goodbye"]);
+        assert_eq!(MyToolTrait::descriptions(&tool), &["This", "Goodbye"]);
+    }
+
+    #[test]
+    fn function_name_is_a_string_constant() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        let schema = MyToolTrait::schema(&tool);
+        let constants: Vec<&serde_json::Value> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|function| &function["properties"]["function_name"]["const"])
+            .collect();
+        assert_eq!(constants, [&serde_json::json!("greet"), &serde_json::json!("goodbye")]);
+    }
+
+    #[test]
+    fn tool_description() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        assert_eq!(
+            MyToolTrait::tool_description(&tool),
+            Some("Greets people.\nAlways polite.")
+        );
+    }
+
+    #[test]
+    fn group_is_in_schema() {
+        let tool = MyTool::new();
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        assert_eq!(MyToolTrait::group(&tool), Some("greetings"));
+        let schema = MyToolTrait::schema(&tool);
+        for function_schema in schema["oneOf"].as_array().unwrap() {
+            assert_eq!(function_schema["x-group"], "greetings");
+        }
+    }
+
+    #[test]
+    fn functions() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let functions: Vec<_> = toolbox.functions().collect();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].0, "greet");
+        assert_eq!(functions[0].1["returns"], "The formatted greeting");
+        assert!(functions[0].1["properties"]["parameters"]["properties"]["greeting"].is_object());
+        assert_eq!(functions[1].0, "goodbye");
+    }
+
+    #[test]
+    fn contract_snapshot() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let snapshot = toolbox.contract_snapshot();
+        let functions = snapshot["functions"].as_array().unwrap();
+        assert_eq!(functions[0]["name"], "goodbye");
+        assert_eq!(functions[1]["name"], "greet");
+        assert_eq!(functions[1]["returns"], "The formatted greeting");
+        assert_eq!(functions[1]["parameters"]["required"], serde_json::json!(["greeting"]));
+        let keys: Vec<&String> = functions[1].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["description", "name", "parameters", "returns"]);
+        assert_eq!(
+            serde_json::to_string(&snapshot).unwrap(),
+            serde_json::to_string(&toolbox.contract_snapshot()).unwrap()
+        );
+    }
+
+    #[test]
+    fn export_schema() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let path = std::env::temp_dir().join("llmtoolbox_export_schema.json");
+        toolbox.export_schema(&path).unwrap();
+        let first = std::fs::read_to_string(&path).unwrap();
+        toolbox.export_schema(&path).unwrap();
+        let second = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(first, second);
+        assert!(first.ends_with("}\n"));
+        let schema: serde_json::Value = serde_json::from_str(&first).unwrap();
+        assert_eq!(&schema, &serde_json::Value::Object(toolbox.schema().clone()));
+        let greet = &schema["oneOf"][0];
+        let keys: Vec<&String> = greet.as_object().unwrap().keys().collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn tool_for_function() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool = toolbox.tool_for("goodbye").unwrap();
+        assert_eq!(tool.function_names(), &["greet", "goodbye"]);
+        assert_eq!(tool.group(), Some("greetings"));
+        assert!(toolbox.tool_for("hello").is_none());
+    }
+}
+
+
+#[cfg(test)]
+pub mod recursive_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// Counts the nodes in the tree
+        /// `tree` - The tree to count
+        #[tool_part]
+        fn count(&self, tree: TreeNode) -> usize {
+            fn count(node: &TreeNode) -> usize {
+                1 + node.children.iter().map(count).sum::<usize>()
+            }
+            count(&tree)
+        }
+    }
+
+    /// A node in a tree
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct TreeNode {
+        pub value: String,
+        pub children: Vec<TreeNode>,
+    }
+
+    #[tokio::test]
+    async fn recursive_schema_keeps_references() {
+        let mut toolbox: llmtoolbox::ToolBox<usize, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let parameters = &toolbox.openai_schema()[0]["function"]["parameters"];
+        assert_eq!(
+            parameters["properties"]["tree"]["properties"]["children"]["items"]["$ref"],
+            "#/definitions/TreeNode"
+        );
+        assert!(parameters["definitions"]["TreeNode"].is_object());
+
+        let tool_call_value = serde_json::json!({
+            "function_name": "count",
+            "parameters": {
+                "tree": {
+                    "value": "root",
+                    "children": [
+                        { "value": "a", "children": [] },
+                        { "value": "b", "children": [{ "value": "c", "children": [] }] }
+                    ]
+                }
+            }
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(count)) => assert_eq!(count, 4),
+            Err(error) => panic!("{error}"),
+        };
+    }
+
+    mod other {
+        #[derive(Debug)]
+        pub struct OtherTool;
+
+        #[llmtool::tool]
+        impl OtherTool {
+            /// Sums the numbers in the tree
+            /// `tree` - The tree to sum
+            #[tool_part]
+            fn sum(&self, tree: TreeNode) -> usize {
+                fn sum(node: &TreeNode) -> usize {
+                    node.value + node.children.iter().map(sum).sum::<usize>()
+                }
+                sum(&tree)
+            }
+        }
+
+        /// A node in a tree of numbers
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        pub struct TreeNode {
+            pub value: usize,
+            pub children: Vec<TreeNode>,
+        }
+    }
+
+    #[test]
+    fn combined_schema_resolves_references() {
+        let mut toolbox: llmtoolbox::ToolBox<usize, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        toolbox.add_tool(other::OtherTool).unwrap();
+        let schema = serde_json::Value::Object(toolbox.schema().clone());
+        let validator = jsonschema::draft7::new(&schema).unwrap();
+
+        let count = serde_json::json!({
+            "function_name": "count",
+            "parameters": {
+                "tree": { "value": "root", "children": [{ "value": "a", "children": [] }] }
+            }
+        });
+        assert!(validator.is_valid(&count));
+        let sum = serde_json::json!({
+            "function_name": "sum",
+            "parameters": {
+                "tree": { "value": 1, "children": [{ "value": 2, "children": [] }] }
+            }
+        });
+        assert!(validator.is_valid(&sum));
+        // the deep `value`s are checked against the definition of each function's own `TreeNode`
+        let wrong_sum = serde_json::json!({
+            "function_name": "sum",
+            "parameters": {
+                "tree": { "value": 1, "children": [{ "value": "a", "children": [] }] }
+            }
+        });
+        assert!(!validator.is_valid(&wrong_sum));
+        let wrong_count = serde_json::json!({
+            "function_name": "count",
+            "parameters": {
+                "tree": { "value": "root", "children": [{ "value": 1, "children": [] }] }
+            }
+        });
+        assert!(!validator.is_valid(&wrong_count));
+
+        toolbox.remove_tool("count");
+        let schema = toolbox.schema();
+        assert_eq!(schema["definitions"].as_object().unwrap().len(), 1);
+        jsonschema::draft7::new(&serde_json::Value::Object(schema.clone())).unwrap();
+    }
+}
+
+#[cfg(test)]
+pub mod string_like_output {
+    use std::{borrow::Cow, sync::Arc};
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// This
+        /// `greeting` - descr
+        #[tool_part]
+        fn greet(&self, greeting: &str) -> Arc<str> {
+            format!("This is the greeting `{greeting}`").into()
+        }
+
+        /// Goodbye
+        #[tool_part]
+        fn goodbye(&self) -> Cow<'static, str> {
+            Cow::Borrowed("Goodbye!")
+        }
+
+        /// Fallible
+        #[tool_part]
+        fn fallible(&self) -> Result<Box<str>, std::io::Error> {
+            Ok("Fallible".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn string_like_outputs_are_strings() {
+        let mut toolbox: llmtoolbox::ToolBox<String, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_call_value = serde_json::json!({
+            "function_name": "goodbye",
+            "parameters": {}
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(tool_result)) => assert_eq!(tool_result, "Goodbye!"),
+            _ => panic!("Should be a string"),
+        };
+
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let tool_call_value = serde_json::json!({
+            "function_name": "fallible",
+            "parameters": {}
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(tool_result)) => assert_eq!(*tool_result.downcast::<String>().unwrap(), "Fallible"),
+            _ => panic!("Should be a string"),
+        };
+    }
+}
+
+
+#[cfg(test)]
+pub mod enum_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        fn new() -> Self {
+            Self
+        }
+
+        /// Finds a user
+        /// `query` - How to find the user
+        #[tool_part]
+        fn find(&self, query: Query) -> String {
+            match query {
+                Query::ById(id) => format!("id {id}"),
+                Query::ByName(name) => format!("name {name}"),
+                Query::All => "all".to_owned(),
+            }
+        }
+
+        /// Finds a user
+        /// `query` - How to find the user
+        #[tool_part]
+        fn find_tagged(&self, query: TaggedQuery) -> String {
+            match query {
+                TaggedQuery::ById(id) => format!("id {id}"),
+                TaggedQuery::ByName(name) => format!("name {name}"),
+            }
+        }
+
+        /// Paints
+        /// `color` - The color to paint with
+        /// `shade` - The shade of the color
+        #[tool_part]
+        fn paint(&self, color: Color, shade: Shade) -> String {
+            format!("{color:?} {shade:?}")
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    pub enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    pub enum Shade {
+        /// Close to white
+        Light,
+        /// Close to black
+        Dark,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub enum Query {
+        ById(u64),
+        ByName(String),
+        All,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[serde(tag = "type", content = "value")]
+    pub enum TaggedQuery {
+        ById(u64),
+        ByName(String),
+    }
+
+    #[tokio::test]
+    async fn variants_are_described() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let openai_schema = toolbox.openai_schema();
+        assert_eq!(
+            openai_schema[0]["function"]["parameters"]["properties"]["query"]["description"],
+            "How to find the user\nThe variants are: `All`, `ById`, `ByName`"
+        );
+        assert_eq!(
+            openai_schema[1]["function"]["parameters"]["properties"]["query"]["description"],
+            "How to find the user\nThe variants are: `ById`, `ByName`"
+        );
+        let tool_call_value = serde_json::json!({
+            "function_name": "find_tagged",
+            "parameters": {
+                "query": { "type": "ByName", "value": "Ferris" }
+            }
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(tool_result)) => assert_eq!(tool_result, "name Ferris"),
+            Err(error) => panic!("{error}"),
+        };
+    }
+
+    #[tokio::test]
+    async fn fieldless_enums_are_flat() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool::new()).unwrap();
+        let properties = &toolbox.openai_schema()[2]["function"]["parameters"]["properties"];
+        assert_eq!(
+            properties["color"],
+            serde_json::json!({
+                "type": "string",
+                "enum": ["Red", "Green", "Blue"],
+                "description": "The color to paint with"
+            })
+        );
+        assert_eq!(
+            properties["shade"],
+            serde_json::json!({
+                "type": "string",
+                "enum": ["Light", "Dark"],
+                "description": "The shade of the color\n`Light` - Close to white\n`Dark` - Close to black"
+            })
+        );
+        let tool_call_value = serde_json::json!({
+            "function_name": "paint",
+            "parameters": { "color": "Blue", "shade": "Dark" }
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(tool_result)) => assert_eq!(tool_result, "Blue Dark"),
+            Err(error) => panic!("{error}"),
+        };
+    }
+}
+
+#[cfg(test)]
+pub mod remove_tool {
+
+    #[derive(Debug)]
+    struct GreetTool;
+
+    #[llmtool::tool]
+    impl GreetTool {
+        /// Greet
+        #[tool_part]
+        fn hello(&self) -> String {
+            "Hello".to_owned()
+        }
+
+        /// Greet
+        #[tool_part]
+        fn goodbye(&self) -> String {
+            "Goodbye".to_owned()
+        }
+    }
+
+    #[derive(Debug)]
+    struct OtherTool;
+
+    #[llmtool::tool]
+    impl OtherTool {
+        /// Other
+        #[tool_part]
+        fn other(&self) -> String {
+            "Other".to_owned()
+        }
+    }
+
+    #[test]
+    fn schema_is_built_once() {
+        use llmtoolbox::Tool;
+        fn schema(tool: &GreetTool) -> &serde_json::Map<String, serde_json::Value> {
+            <GreetTool as Tool<String, std::convert::Infallible>>::schema(tool)
+        }
+        let (first, second) = (GreetTool, GreetTool);
+        assert!(std::ptr::eq(schema(&first), schema(&second)));
+    }
+
+    #[test]
+    fn counts_and_iterates_tools() {
+        let mut toolbox: llmtoolbox::ToolBoxLocal<String, std::convert::Infallible> =
+            llmtoolbox::ToolBoxLocal::new();
+        assert!(toolbox.is_empty());
+        toolbox.add_tool(GreetTool).unwrap();
+        toolbox.add_tool(OtherTool).unwrap();
+        assert_eq!(toolbox.len(), 2);
+        assert!(!toolbox.is_empty());
+        let function_names: Vec<&str> = toolbox.iter().flat_map(|tool| tool.function_names()).copied().collect();
+        assert_eq!(function_names, vec!["hello", "goodbye", "other"]);
+        toolbox.remove_tool("other");
+        assert_eq!(toolbox.len(), 1);
+    }
+
+    #[test]
+    fn function_schema_by_name() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(GreetTool).unwrap();
+        toolbox.add_tool(OtherTool).unwrap();
+        let function_schema = toolbox.function_schema("other").unwrap();
+        assert_eq!(function_schema["properties"]["function_name"]["const"], "other");
+        assert!(toolbox.function_schema("missing").is_none());
+    }
+
+    #[test]
+    fn per_tool_schemas_are_not_cloned() {
+        use llmtoolbox::Tool;
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(GreetTool).unwrap();
+        toolbox.add_tool(OtherTool).unwrap();
+        let schemas: Vec<_> = toolbox.per_tool_schemas().collect();
+        assert_eq!(schemas.len(), 2);
+        assert!(std::ptr::eq(
+            schemas[1],
+            <OtherTool as Tool<String, std::convert::Infallible>>::schema(&OtherTool)
+        ));
+    }
+
+    #[test]
+    fn removes_all_functions_of_the_tool() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(GreetTool).unwrap();
+        toolbox.add_tool(OtherTool).unwrap();
+        assert_eq!(toolbox.schema()["oneOf"].as_array().unwrap().len(), 3);
+        assert!(toolbox.remove_tool("goodbye"));
+        assert!(!toolbox.remove_tool("goodbye"));
+        assert!(!toolbox.function_exists("hello"));
+        assert!(toolbox.function_exists("other"));
+        let functions = toolbox.schema()["oneOf"].as_array().unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0]["properties"]["function_name"]["const"], "other");
+    }
+}
+
+#[cfg(test)]
+pub mod array_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Joins words
+        /// `words` - the words to join
+        /// `separator` - what to put between the words
+        #[tool_part]
+        fn join(&self, words: Vec<String>, separator: &str) -> String {
+            words.join(separator)
+        }
+
+        /// Sums numbers
+        /// `numbers` - the numbers to sum
+        /// `query` - unused
+        #[tool_part]
+        fn sum(&self, numbers: &[i64], query: Option<u8>) -> String {
+            let _ = query;
+            numbers.iter().sum::<i64>().to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn arrays_are_inlined() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let openai_schema = toolbox.openai_schema();
+        assert_eq!(
+            openai_schema[0]["function"]["parameters"]["properties"]["words"],
+            serde_json::json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "the words to join"
+            })
+        );
+        assert_eq!(
+            openai_schema[1]["function"]["parameters"]["properties"]["numbers"],
+            serde_json::json!({
+                "type": "array",
+                "items": { "type": "integer" },
+                "description": "the numbers to sum"
+            })
+        );
+        let tool_call_value = serde_json::json!({
+            "function_name": "sum",
+            "parameters": { "numbers": [1, 2, 3], "query": 1 }
+        });
+        match toolbox.call_from_value(tool_call_value).await {
+            Ok(Ok(tool_result)) => assert_eq!(tool_result, "6"),
+            Err(error) => panic!("{error}"),
+        };
+    }
+}
+
+#[cfg(test)]
+pub mod unified_error {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool(error = MyError)]
+    impl MyTool {
+        /// Parses a number
+        /// `text` - the text to parse
+        #[tool_part]
+        fn parse(&self, text: String) -> Result<i64, std::num::ParseIntError> {
+            text.parse()
+        }
+
+        /// Fails
+        #[tool_part]
+        fn fail(&self) -> Result<i64, std::fmt::Error> {
+            Err(std::fmt::Error)
+        }
+
+        /// Answers
+        #[tool_part]
+        fn answer(&self) -> i64 {
+            42
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum MyError {
+        Parse,
+        Format,
+    }
+
+    impl std::fmt::Display for MyError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl std::error::Error for MyError {}
+
+    impl From<std::num::ParseIntError> for MyError {
+        fn from(_: std::num::ParseIntError) -> Self {
+            MyError::Parse
+        }
+    }
+
+    impl From<std::fmt::Error> for MyError {
+        fn from(_: std::fmt::Error) -> Self {
+            MyError::Format
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_are_converted() {
+        let mut toolbox: llmtoolbox::ToolBox<i64, MyError> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "parse", "parameters": {"text": "x"}}))
+            .await;
+        assert_eq!(result.unwrap(), Err(MyError::Parse));
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "fail", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap(), Err(MyError::Format));
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "answer", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap(), Ok(42));
+    }
+}
+
+#[cfg(test)]
+pub mod optional_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Describes the arguments
+        /// `count` - how many
+        /// `name` - the name
+        /// `nickname` - the nickname, `null` to clear it
+        /// `topic` - the topic
+        #[tool_part]
+        fn describe(
+            &self,
+            count: Option<u32>,
+            name: Option<&str>,
+            nickname: Option<Option<String>>,
+            topic: Option<Topic>,
+        ) -> String {
+            format!("{count:?} {name:?} {nickname:?} {:?}", topic.map(|topic| topic.topic))
+        }
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct Topic {
+        pub topic: String,
+    }
+
+    #[tokio::test]
+    async fn optional_parameters() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let parameters = &toolbox.openai_schema()[0]["function"]["parameters"];
+        assert_eq!(parameters["required"], serde_json::json!([]));
+        assert_eq!(parameters["properties"]["count"]["type"], serde_json::json!(["integer", "null"]));
+        assert_eq!(parameters["properties"]["count"]["minimum"], 0);
+        assert_eq!(parameters["properties"]["count"]["maximum"], u32::MAX);
+        assert_eq!(parameters["properties"]["name"]["type"], serde_json::json!(["string", "null"]));
+        assert_eq!(parameters["properties"]["nickname"]["type"], serde_json::json!(["string", "null"]));
+
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "describe", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "None None None None");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "describe", "parameters": {
+                "count": null,
+                "name": "Ferris",
+                "nickname": null,
+                "topic": { "topic": "crabs" }
+            }}))
+            .await;
+        assert_eq!(
+            result.unwrap().unwrap(),
+            "None Some(\"Ferris\") Some(None) Some(\"crabs\")"
+        );
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "describe", "parameters": {
+                "count": 2,
+                "nickname": "Crab"
+            }}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Some(2) None Some(Some(\"Crab\")) None");
+    }
+}
+
+#[cfg(test)]
+pub mod run_stream {
+    use futures_util::StreamExt;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Waits and then echoes
+        /// `millis` - how long to wait
+        #[tool_part]
+        async fn wait(&self, millis: u64) -> u64 {
+            tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+            millis
+        }
+    }
+
+    #[tokio::test]
+    async fn yields_in_completion_order() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let function_calls = [60, 0, 30].map(|millis| {
+            toolbox
+                .into_function_call_from_value(serde_json::json!({
+                    "function_name": "wait",
+                    "parameters": { "millis": millis }
+                }))
+                .unwrap()
+        });
+        let results: Vec<u64> = toolbox
+            .run_stream(function_calls)
+            .map(|(_, result)| result.unwrap().unwrap())
+            .collect()
+            .await;
+        assert_eq!(results, vec![0, 30, 60]);
+    }
+
+    #[tokio::test]
+    async fn sequence_stops_at_first_error() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let function_calls = [
+            serde_json::json!({"function_name": "wait", "parameters": { "millis": 20 }}),
+            serde_json::json!({"function_name": "wait", "parameters": { "millis": 0 }}),
+            serde_json::json!({"function_name": "missing", "parameters": {}}),
+            serde_json::json!({"function_name": "wait", "parameters": { "millis": 0 }}),
+        ]
+        .map(|function_call| toolbox.into_function_call_from_value(function_call).unwrap());
+        let results = toolbox.call_sequence(function_calls).await;
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[0], Ok(Ok(20))));
+        assert!(matches!(results[1], Ok(Ok(0))));
+        assert!(matches!(results[2], Err(llmtoolbox::FunctionCallError::FunctionNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn sequence_without_errors_has_a_result_for_each_call() {
+        let mut toolbox: llmtoolbox::ToolBoxLocal<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBoxLocal::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let function_calls = [
+            serde_json::json!({"function_name": "wait", "parameters": { "millis": 5 }}),
+            serde_json::json!({"function_name": "wait", "parameters": { "millis": 0 }}),
+        ]
+        .map(|function_call| toolbox.into_function_call_from_value(function_call).unwrap());
+        let results = toolbox.call_sequence(function_calls).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert!(toolbox.call_sequence([]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn many_from_value_keeps_call_order() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let results = toolbox
+            .call_many_from_value(serde_json::json!([
+                {"function_name": "wait", "parameters": { "millis": 60 }},
+                {"function_name": "missing", "parameters": {}},
+                {"parameters": {}},
+                {"function_name": "wait", "parameters": { "millis": 0 }},
+            ]))
+            .await;
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0], Ok(Ok(60))));
+        assert!(matches!(results[1], Err(llmtoolbox::FunctionCallError::FunctionNotFound { .. })));
+        assert!(matches!(results[2], Err(llmtoolbox::FunctionCallError::MissingFunctionName { .. })));
+        assert!(matches!(results[3], Ok(Ok(0))));
+
+        let results = toolbox
+            .call_many_from_value(serde_json::json!({"function_name": "wait", "parameters": { "millis": 5 }}))
+            .await;
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Ok(Ok(5))));
+    }
+
+    #[tokio::test]
+    async fn many_concurrent_runs_at_once() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let toolbox = std::sync::Arc::new(toolbox);
+        let function_calls = [50, 0, 50, 50].map(|millis| {
+            toolbox
+                .into_function_call_from_value(serde_json::json!({
+                    "function_name": "wait",
+                    "parameters": { "millis": millis }
+                }))
+                .unwrap()
+        });
+        let start = std::time::Instant::now();
+        let results = tokio::spawn({
+            let toolbox = toolbox.clone();
+            async move { toolbox.call_many_concurrent(function_calls).await }
+        })
+        .await
+        .unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_millis(150));
+        let results: Vec<u64> = results.into_iter().map(|result| result.unwrap().unwrap()).collect();
+        assert_eq!(results, vec![50, 0, 50, 50]);
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let timeout = std::time::Duration::from_millis(20);
+        let result = toolbox
+            .call_from_value_with_timeout(serde_json::json!({"function_name": "wait", "parameters": {"millis": 1000}}), timeout)
+            .await;
+        match result {
+            Err(llmtoolbox::FunctionCallError::Timeout { function_name, elapsed }) => {
+                assert_eq!(function_name, "wait");
+                assert_eq!(elapsed, timeout);
+            }
+            _ => panic!("expected a timeout"),
+        }
+        let result = toolbox
+            .call_from_value_with_timeout(serde_json::json!({"function_name": "wait", "parameters": {"millis": 0}}), timeout)
+            .await;
+        assert_eq!(result.unwrap().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_by_abort() {
+        let mut toolbox: llmtoolbox::ToolBox<u64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+        let abort = async {
+            let _ = cancelled.await;
+        };
+        let call = toolbox.call_from_value_cancellable(serde_json::json!({"function_name": "wait", "parameters": {"millis": 1000}}), abort);
+        cancel.send(()).unwrap();
+        match call.await {
+            Err(llmtoolbox::FunctionCallError::Cancelled { function_name }) => assert_eq!(function_name, "wait"),
+            _ => panic!("expected a cancellation"),
+        }
+        let result = toolbox
+            .call_from_value_cancellable(serde_json::json!({"function_name": "wait", "parameters": {"millis": 0}}), std::future::pending())
+            .await;
+        assert_eq!(result.unwrap().unwrap(), 0);
+    }
+}
+
+#[cfg(test)]
+pub mod associated_function {
+
+    #[derive(Debug)]
+    struct MathTool;
+
+    #[llmtool::tool]
+    impl MathTool {
+        /// Adds two numbers
+        /// `a` - the first number
+        /// `b` - the second number
+        #[tool_part]
+        fn add(a: i64, b: i64) -> i64 {
+            a + b
+        }
+
+        /// Negates a number
+        /// `a` - the number
+        #[tool_part]
+        async fn negate(a: i64) -> i64 {
+            -a
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_without_receiver() {
+        let mut toolbox: llmtoolbox::ToolBox<i64, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MathTool).unwrap();
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "add", "parameters": {"a": 1, "b": 2}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), 3);
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "negate", "parameters": {"a": 1}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), -1);
+    }
+}
+
+#[cfg(test)]
+pub mod coerce_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Describes the flags
+        /// `loose` - a loosely typed flag
+        /// `strict` - a strictly typed flag
+        #[tool_part]
+        fn flags(&self, #[tool_param(coerce)] loose: bool, strict: bool) -> String {
+            format!("{loose} {strict}")
+        }
+    }
+
+    #[tokio::test]
+    async fn coerces_only_when_enabled() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        for (loose, expected) in [
+            (serde_json::json!("TRUE"), "true false"),
+            (serde_json::json!("false"), "false false"),
+            (serde_json::json!(1), "true false"),
+            (serde_json::json!(0), "false false"),
+            (serde_json::json!(true), "true false"),
+        ] {
+            let result = toolbox
+                .call_from_value(serde_json::json!({"function_name": "flags", "parameters": {"loose": loose, "strict": false}}))
+                .await;
+            assert_eq!(result.unwrap().unwrap(), expected);
+        }
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "flags", "parameters": {"loose": 2, "strict": false}}))
+            .await;
+        assert!(result.is_err());
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "flags", "parameters": {"loose": "yes", "strict": false}}))
+            .await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains(r#"expected a bool, "true"/"false" or 0/1, got "yes""#), "{error}");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "flags", "parameters": {"loose": true, "strict": "true"}}))
+            .await;
+        assert!(result.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod parameter_constraints {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Rates a greeting
+        /// `greeting` - the greeting
+        /// `rating` - the rating
+        /// `weight` - the weight
+        #[tool_part]
+        fn rate(
+            &self,
+            #[tool_param(min_length = 1, max_length = 100, pattern = "^[a-z]+$")] greeting: String,
+            #[tool_param(min = 0, max = 10)] rating: u8,
+            #[tool_param(min = -1.5)] weight: Option<f64>,
+        ) -> String {
+            format!("{greeting} {rating} {weight:?}")
+        }
+    }
+
+    #[test]
+    fn constraints_are_in_schema() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let properties = &toolbox.openai_schema()[0]["function"]["parameters"]["properties"];
+        assert_eq!(
+            properties["greeting"],
+            serde_json::json!({
+                "type": "string",
+                "minLength": 1,
+                "maxLength": 100,
+                "pattern": "^[a-z]+$",
+                "description": "the greeting"
+            })
+        );
+        assert_eq!(properties["rating"]["minimum"], 0);
+        assert_eq!(properties["rating"]["maximum"], 10);
+        assert_eq!(properties["weight"]["minimum"], -1.5);
+    }
+}
+
+#[cfg(test)]
+pub mod partial_arguments {
+    use llmtoolbox::{FunctionCallArgs, PartialFunctionCall};
+
+    fn parameters(partial_json: &str) -> (bool, FunctionCallArgs) {
+        match FunctionCallArgs::try_from_partial("search", partial_json).unwrap() {
+            PartialFunctionCall::Complete(args) => (true, args),
+            PartialFunctionCall::Incomplete(args) => (false, args),
+        }
+    }
+
+    fn args(parameters: serde_json::Value) -> FunctionCallArgs {
+        let toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox
+            .into_function_call_from_value(serde_json::json!({
+                "function_name": "search",
+                "parameters": parameters
+            }))
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_truncated_arguments() {
+        assert_eq!(parameters(""), (false, args(serde_json::json!({}))));
+        assert_eq!(parameters(r#"{"query": "rust"#), (false, args(serde_json::json!({"query": "rust"}))));
+        assert_eq!(
+            parameters(r#"{"query": "rust", "tags": ["a", "b"#),
+            (false, args(serde_json::json!({"query": "rust", "tags": ["a", "b"]})))
+        );
+        assert_eq!(parameters(r#"{"query": "rust", "lim"#), (false, args(serde_json::json!({"query": "rust"}))));
+        assert_eq!(parameters(r#"{"query": "rust", "exact": tr"#), (false, args(serde_json::json!({"query": "rust"}))));
+        assert_eq!(parameters(r#"{"query": "a\"#), (false, args(serde_json::json!({"query": "a"}))));
+        assert_eq!(
+            parameters(r#"{"query": "rust", "limit": 5}"#),
+            (true, args(serde_json::json!({"query": "rust", "limit": 5})))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(FunctionCallArgs::try_from_partial("search", r#"{"query": }"#).is_err());
+        assert!(FunctionCallArgs::try_from_partial("search", "[1, 2]").is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod bind_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Lists the orders of a tenant
+        /// `tenant_id` - the tenant
+        /// `status` - the status of the orders
+        #[tool_part]
+        fn orders(&self, tenant_id: u64, status: String) -> String {
+            format!("{status} orders of {tenant_id}")
+        }
+
+        /// Greets
+        #[tool_part]
+        fn hello(&self) -> String {
+            "Hello".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn injects_and_hides_parameter() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let view = toolbox.bind_parameter("tenant_id", serde_json::json!(7));
+
+        let parameters = &view.openai_schema()[0]["function"]["parameters"];
+        assert_eq!(parameters["required"], serde_json::json!(["status"]));
+        assert!(parameters["properties"].get("tenant_id").is_none());
+        assert!(toolbox.openai_schema()[0]["function"]["parameters"]["properties"]
+            .get("tenant_id")
+            .is_some());
+
+        let result = view
+            .call_from_value(serde_json::json!({"function_name": "orders", "parameters": {"status": "open", "tenant_id": 1}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "open orders of 7");
+        let result = view
+            .call_from_value(serde_json::json!({"function_name": "hello", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Hello");
+    }
+
+    #[test]
+    fn hides_parameter_from_every_schema() {
+        let mut toolbox: llmtoolbox::ToolBoxLocal<String, std::convert::Infallible> =
+            llmtoolbox::ToolBoxLocal::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let view = toolbox.bind_parameter("tenant_id", serde_json::json!(7));
+
+        let parameters = &view.strict_openai_schema()[0]["function"]["parameters"];
+        assert_eq!(parameters["required"], serde_json::json!(["status"]));
+        assert!(parameters["properties"].get("tenant_id").is_none());
+
+        let parameters = &view.gemini_schema()["functionDeclarations"][0]["parameters"];
+        assert_eq!(parameters["required"], serde_json::json!(["status"]));
+        assert!(parameters["properties"].get("tenant_id").is_none());
+
+        let schema = view.schema();
+        let parameters = &schema["oneOf"][0]["properties"]["parameters"];
+        assert_eq!(parameters["required"], serde_json::json!(["status"]));
+        assert!(parameters["properties"].get("tenant_id").is_none());
+        assert!(toolbox.schema()["oneOf"][0]["properties"]["parameters"]["properties"]
+            .get("tenant_id")
+            .is_some());
+
+        let contract = view.contract_snapshot().to_string();
+        assert!(!contract.contains("tenant_id"), "{contract}");
+        #[cfg(feature = "mcp")]
+        assert!(!serde_json::json!(view.mcp_tool_list()).to_string().contains("tenant_id"));
+    }
+}
+
+#[cfg(test)]
+pub mod reject_unknown_parameters {
+    use llmtoolbox::FunctionCallError;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Greets someone
+        /// `name` - who to greet
+        #[tool_part]
+        fn greet(&self, name: String) -> String {
+            format!("Hello {name}")
+        }
+
+        /// Says goodbye
+        #[tool_part]
+        fn goodbye(&self) -> String {
+            "Goodbye".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_parameters_in_strict_mode() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let goodbye = serde_json::json!({"function_name": "goodbye", "parameters": {"name": "Ferris"}});
+        let result = toolbox.call_from_value(goodbye.clone()).await;
+        assert_eq!(result.unwrap().unwrap(), "Goodbye");
+
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new().with_reject_unknown_parameters(true);
+        toolbox.add_tool(MyTool).unwrap();
+        match toolbox.call_from_value(goodbye).await {
+            Err(FunctionCallError::UnknownParameters { function_name, parameter_names }) => {
+                assert_eq!(function_name, "goodbye");
+                assert_eq!(parameter_names, vec!["name".to_owned()]);
+            }
+            _ => panic!("expected unknown parameters"),
+        }
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"name": "Ferris", "age": 3}}))
+            .await;
+        assert!(matches!(result, Err(FunctionCallError::UnknownParameters { .. })));
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "greet", "parameters": {"name": "Ferris"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Hello Ferris");
+    }
+}
+
+#[cfg(test)]
+pub mod box_any_output {
+    use std::any::Any;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Parses a number
+        /// `text` - the text to parse
+        #[tool_part]
+        fn parse(&self, text: String) -> Result<Box<dyn Any>, std::num::ParseIntError> {
+            Ok(Box::new(text.parse::<i64>()?))
+        }
+
+        /// Greets
+        #[tool_part]
+        fn hello(&self) -> String {
+            "Hello".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn output_is_not_boxed_twice() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn Any>, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "parse", "parameters": {"text": "42"}}))
+            .await;
+        let output = result.unwrap().unwrap();
+        assert_eq!(*output.downcast::<i64>().unwrap(), 42);
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "hello", "parameters": {}}))
+            .await;
+        assert_eq!(*result.unwrap().unwrap().downcast::<String>().unwrap(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn call_and_downcast() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn Any>, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let result = toolbox
+            .call_and_downcast::<i64>(serde_json::json!({"function_name": "parse", "parameters": {"text": "42"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), 42);
+        let result = toolbox
+            .call_and_downcast::<i64>(serde_json::json!({"function_name": "parse", "parameters": {"text": "forty two"}}))
+            .await;
+        assert!(result.unwrap().is_err());
+        let result = toolbox
+            .call_and_downcast::<i64>(serde_json::json!({"function_name": "hello", "parameters": {}}))
+            .await;
+        match result {
+            Err(llmtoolbox::FunctionCallError::OutputTypeMismatch { function_name, expected }) => {
+                assert_eq!(function_name, "hello");
+                assert_eq!(expected, "i64");
+            }
+            _ => panic!("expected an output type mismatch"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod text_output {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool(text_output)]
+    impl MyTool {
+        /// Finds a point
+        #[tool_part]
+        fn point(&self) -> Point {
+            Point { x: 1, y: 2 }
+        }
+
+        /// Counts
+        #[tool_part]
+        fn count(&self) -> u32 {
+            3
+        }
+
+        /// Parses a number
+        /// `text` - the text to parse
+        #[tool_part]
+        fn parse(&self, text: String) -> Result<f64, std::num::ParseFloatError> {
+            text.parse()
+        }
+    }
+
+    pub struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    impl std::fmt::Display for Point {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "({}, {})", self.x, self.y)
+        }
+    }
+
+    #[tokio::test]
+    async fn outputs_are_strings() {
+        let mut toolbox: llmtoolbox::ToolBox<String, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        for (function_name, parameters, expected) in [
+            ("point", serde_json::json!({}), "(1, 2)"),
+            ("count", serde_json::json!({}), "3"),
+            ("parse", serde_json::json!({"text": "1.5"}), "1.5"),
+        ] {
+            let result = toolbox
+                .call_from_value(serde_json::json!({"function_name": function_name, "parameters": parameters}))
+                .await;
+            assert_eq!(result.unwrap().unwrap(), expected);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod retry {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(Debug, Default)]
+    struct MyTool {
+        reads: AtomicUsize,
+        emails: Arc<AtomicUsize>,
+    }
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Reads a value, fails the first two times
+        #[tool_part(idempotent)]
+        fn read(&self) -> Result<usize, std::fmt::Error> {
+            let reads = self.reads.fetch_add(1, Ordering::SeqCst) + 1;
+            if reads < 3 {
+                return Err(std::fmt::Error);
+            }
+            Ok(reads)
+        }
+
+        /// Sends an email, always fails
+        #[tool_part]
+        fn send_email(&self) -> Result<usize, std::fmt::Error> {
+            self.emails.fetch_add(1, Ordering::SeqCst);
+            Err(std::fmt::Error)
+        }
+    }
+
+    #[tokio::test]
+    async fn only_idempotent_functions_are_retried() {
+        use llmtoolbox::Tool;
+        let tool = MyTool::default();
+        assert!(<MyTool as Tool<usize, std::fmt::Error>>::is_idempotent(&tool, "read"));
+        assert!(!<MyTool as Tool<usize, std::fmt::Error>>::is_idempotent(&tool, "send_email"));
+
+        let emails = tool.emails.clone();
+        let mut toolbox: llmtoolbox::ToolBox<usize, std::fmt::Error> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(tool).unwrap();
+        let read = toolbox
+            .into_function_call_from_value(serde_json::json!({"function_name": "read", "parameters": {}}))
+            .unwrap();
+        assert_eq!(toolbox.call_with_retry(read, 5).await.unwrap(), Ok(3));
+        let send_email = toolbox
+            .into_function_call_from_value(serde_json::json!({"function_name": "send_email", "parameters": {}}))
+            .unwrap();
+        assert!(toolbox.call_with_retry(send_email, 5).await.unwrap().is_err());
+        assert_eq!(emails.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[cfg(test)]
+pub mod renamed_function {
+    #[derive(Debug)]
+    struct WeatherTool;
+
+    #[llmtool::tool]
+    impl WeatherTool {
+        /// Gets the weather
+        /// `city` - The city to get the weather for
+        #[tool_part(name = "get_weather")]
+        fn fetch_weather_internal(&self, city: String) -> String {
+            format!("Sunny in {city}")
+        }
+    }
+
+    #[derive(Debug)]
+    struct OtherWeatherTool;
+
+    #[llmtool::tool]
+    impl OtherWeatherTool {
+        /// Gets the weather
+        /// `city` - The city to get the weather for
+        #[tool_part]
+        fn get_weather(&self, city: String) -> String {
+            format!("Rainy in {city}")
+        }
+    }
+
+    #[tokio::test]
+    async fn exposed_under_the_overridden_name() {
+        use llmtoolbox::Tool;
+        assert_eq!(<WeatherTool as Tool<String, std::convert::Infallible>>::function_names(&WeatherTool), &["get_weather"]);
+
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(WeatherTool).unwrap();
+        let constant = &toolbox.schema()["oneOf"][0]["properties"]["function_name"]["const"];
+        assert_eq!(constant, "get_weather");
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "get_weather", "parameters": {"city": "Paris"}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("Sunny in Paris".to_owned()));
+        assert!(toolbox
+            .call_from_value(serde_json::json!({"function_name": "fetch_weather_internal", "parameters": {"city": "Paris"}}))
+            .await
+            .is_err());
+        assert_eq!(toolbox.add_tool(OtherWeatherTool).unwrap_err().conflicting_name, "get_weather");
+    }
+}
+
+#[cfg(test)]
+pub mod renamed_parameter {
+    #[derive(Debug)]
+    struct SearchTool;
+
+    #[llmtool::tool]
+    impl SearchTool {
+        /// Searches the web
+        /// `search_terms` - What to search for
+        /// `max` - The most results to return
+        #[tool_part]
+        fn search(&self, #[tool_param(rename = "query")] search_terms: String, #[tool_param(rename = "limit")] max: Option<u32>) -> String {
+            format!("{search_terms} {max:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn exposed_under_the_renamed_key() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(SearchTool).unwrap();
+        let parameters = &toolbox.schema()["oneOf"][0]["properties"]["parameters"];
+        assert_eq!(parameters["properties"]["query"]["description"], "What to search for");
+        assert!(parameters["properties"]["search_terms"].is_null());
+        assert!(parameters["properties"]["limit"].is_object());
+        assert_eq!(parameters["required"], serde_json::json!(["query"]));
+
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "search", "parameters": {"query": "rust", "limit": 3}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("rust Some(3)".to_owned()));
+        assert!(toolbox
+            .call_from_value(serde_json::json!({"function_name": "search", "parameters": {"search_terms": "rust"}}))
+            .await
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod returns_doc_line {
+    use llmtoolbox::Tool;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Greets someone
+        /// `name` - Who to greet
+        /// returns - the formatted greeting
+        #[tool_part]
+        fn greet(&self, name: &str) -> String {
+            format!("Hello {name}")
+        }
+    }
+
+    #[test]
+    fn returns_is_taken_from_the_doc_comment() {
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        assert_eq!(
+            MyToolTrait::returns_description(&MyTool, "greet"),
+            Some("the formatted greeting")
+        );
+        let schema = MyToolTrait::schema(&MyTool);
+        let function = &schema["oneOf"][0];
+        assert_eq!(function["returns"], "the formatted greeting");
+        assert!(!function["description"].as_str().unwrap().contains("returns"));
+    }
+}
+
+#[cfg(test)]
+pub mod call_hooks {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Adds two numbers
+        /// `a` - The first number
+        /// `b` - The second number
+        #[tool_part]
+        fn add(&self, a: u32, b: u32) -> u32 {
+            a + b
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_run_around_each_call() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let mut toolbox: llmtoolbox::ToolBox<u32, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        toolbox.set_on_call({
+            let calls = calls.clone();
+            move |function_name, parameters| {
+                calls.lock().unwrap().push((function_name.to_owned(), parameters.clone()));
+            }
+        });
+        toolbox.set_on_result({
+            let results = results.clone();
+            move |function_name, result, _elapsed| {
+                results.lock().unwrap().push((function_name.to_owned(), *result.as_ref().unwrap().as_ref().unwrap()));
+            }
+        });
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "add", "parameters": {"a": 1, "b": 2}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok(3));
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, "add");
+        assert_eq!(calls[0].1["a"], 1);
+        assert_eq!(*results.lock().unwrap(), vec![("add".to_owned(), 3)]);
+    }
+}
+
+#[cfg(test)]
+pub mod enum_tool {
+
+    #[derive(Debug)]
+    enum TrafficLight {
+        Red,
+        Green,
+    }
+
+    #[llmtool::tool]
+    impl TrafficLight {
+        /// The current color of the light
+        #[tool_part]
+        fn color(&self) -> String {
+            match self {
+                TrafficLight::Red => "red".to_owned(),
+                TrafficLight::Green => "green".to_owned(),
+            }
+        }
+
+        /// Whether a car may drive through the light
+        /// `emergency` - Whether the car is an emergency vehicle
+        #[tool_part]
+        fn can_drive(&self, emergency: bool) -> String {
+            (emergency || matches!(self, TrafficLight::Green)).to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn enum_is_a_tool() {
+        let mut toolbox: llmtoolbox::ToolBoxLocal<String, std::convert::Infallible> =
+            llmtoolbox::ToolBoxLocal::new();
+        toolbox.add_tool(TrafficLight::Red).unwrap();
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "color", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "red");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "can_drive", "parameters": {"emergency": false}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "false");
+
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(TrafficLight::Green).unwrap();
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "can_drive", "parameters": {"emergency": false}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "true");
+    }
+}
+
+#[cfg(test)]
+pub mod gemini_schema {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Gets the forecast
+        /// `place` - Where to get the forecast for
+        /// `days` - How many days to forecast
+        #[tool_part]
+        fn forecast(&self, place: Place, days: Option<u8>) -> String {
+            format!("{} {days:?}", place.name)
+        }
+    }
+
+    /// A named place
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct Place {
+        pub name: String,
+        pub location: Option<Location>,
+    }
+
+    /// A point on the globe
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct Location {
+        pub latitude: f64,
+        pub longitude: f64,
+    }
+
+    #[test]
+    fn function_declarations() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let schema = toolbox.gemini_schema();
+        let declaration = &schema["functionDeclarations"][0];
+        assert_eq!(declaration["name"], "forecast");
+        let parameters = &declaration["parameters"];
+        assert!(parameters.get("$schema").is_none());
+        assert!(parameters.get("definitions").is_none());
+        assert_eq!(parameters["properties"]["days"]["type"], "integer");
+        assert_eq!(parameters["properties"]["days"]["nullable"], true);
+        let location = &parameters["properties"]["place"]["properties"]["location"];
+        assert_eq!(location["nullable"], true);
+        assert_eq!(location["properties"]["latitude"]["type"], "number");
+        assert!(location.get("$ref").is_none());
+    }
+
+    #[test]
+    fn nested_types_are_inlined() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let parameters = &toolbox.schema()["oneOf"][0]["properties"]["parameters"];
+        assert!(parameters.get("definitions").is_none());
+        let location = &parameters["properties"]["place"]["properties"]["location"]["anyOf"][0];
+        assert!(location.get("$ref").is_none());
+        assert_eq!(location["description"], "A point on the globe");
+        assert_eq!(location["properties"]["latitude"]["type"], "number");
+    }
+}
+
+#[cfg(test)]
+pub mod strict_openai_schema {
+    use super::gemini_schema::Place;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Gets the forecast
+        /// `place` - Where to get the forecast for
+        /// `days` - How many days to forecast
+        #[tool_part]
+        fn forecast(&self, place: Place, days: Option<u8>) -> String {
+            format!("{} {days:?}", place.name)
+        }
+    }
+
+    #[tokio::test]
+    async fn every_property_is_required() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let function = &toolbox.strict_openai_schema()[0]["function"];
+        assert_eq!(function["strict"], true);
+        let parameters = &function["parameters"];
+        assert_eq!(parameters["additionalProperties"], false);
+        assert_eq!(parameters["required"], serde_json::json!(["days", "place"]));
+        assert_eq!(parameters["properties"]["days"]["type"], serde_json::json!(["integer", "null"]));
+        let place = &parameters["properties"]["place"];
+        assert_eq!(place["additionalProperties"], false);
+        assert_eq!(place["required"], serde_json::json!(["location", "name"]));
+        let location = &place["properties"]["location"]["anyOf"][0];
+        assert_eq!(location["additionalProperties"], false);
+
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "forecast", "parameters": {"place": {"name": "Paris", "location": null}, "days": null}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Paris None");
+    }
+}
+
+#[cfg(test)]
+pub mod two_generic_output {
+    use std::collections::HashMap;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Counts the words in the text
+        /// `text` - The text to count
+        #[tool_part]
+        fn word_counts(&self, text: String) -> HashMap<String, u32> {
+            let mut counts = HashMap::new();
+            for word in text.split_whitespace() {
+                *counts.entry(word.to_owned()).or_insert(0) += 1;
+            }
+            counts
+        }
+    }
+
+    #[tokio::test]
+    async fn map_output_is_not_a_result() {
+        let mut toolbox: llmtoolbox::ToolBox<HashMap<String, u32>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let counts = toolbox
+            .call_from_value(serde_json::json!({"function_name": "word_counts", "parameters": {"text": "a b a"}}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts["a"], 2);
+        assert_eq!(counts["b"], 1);
+    }
+}
+
+#[cfg(test)]
+pub mod unit_output {
+    use std::any::Any;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct EmailTool {
+        sent: AtomicUsize,
+    }
+
+    #[llmtool::tool]
+    impl EmailTool {
+        /// Sends an email
+        /// `to` - Who to send the email to
+        #[tool_part]
+        fn send(&self, to: String) {
+            let _ = to;
+            self.sent.fetch_add(1, Ordering::SeqCst);
+        }
+
+        /// The number of emails sent
+        #[tool_part]
+        fn sent(&self) -> usize {
+            self.sent.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn omitted_return_type_is_unit() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(EmailTool::default()).unwrap();
+        let result = toolbox
+            .call_and_downcast::<()>(serde_json::json!({"function_name": "send", "parameters": {"to": "a@b.c"}}))
+            .await;
+        assert_eq!(result.unwrap(), Ok(()));
+        let result = toolbox
+            .call_and_downcast::<usize>(serde_json::json!({"function_name": "sent", "parameters": {}}))
+            .await;
+        assert_eq!(result.unwrap(), Ok(1));
+    }
+}
+
+#[cfg(test)]
+pub mod flatten_parameter {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Books a flight
+        /// `booking` - The flight to book
+        #[tool_part(flatten)]
+        fn book(&self, booking: Booking) -> String {
+            format!("{} to {}", booking.from, booking.to)
+        }
+
+        /// Cancels a flight
+        /// `booking` - The flight to cancel
+        #[tool_part(flatten)]
+        fn cancel(&self, booking: &Booking) -> String {
+            format!("Cancelled {} to {}", booking.from, booking.to)
+        }
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct Booking {
+        /// The departure airport
+        pub from: String,
+        /// The arrival airport
+        pub to: String,
+    }
+
+    #[tokio::test]
+    async fn parameters_are_the_struct() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let parameters = &toolbox.openai_schema()[0]["function"]["parameters"];
+        assert_eq!(parameters["type"], "object");
+        assert_eq!(parameters["properties"]["from"]["description"], "The departure airport");
+        assert!(parameters["properties"]["booking"].is_null());
+
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "book", "parameters": {"from": "CDG", "to": "JFK"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "CDG to JFK");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "cancel", "parameters": {"from": "CDG", "to": "JFK"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Cancelled CDG to JFK");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "book", "parameters": {"from": "CDG"}}))
+            .await;
+        assert!(result.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod resolve_call {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(Debug, Default)]
+    struct EmailTool {
+        sent: Arc<AtomicUsize>,
+    }
+
+    #[llmtool::tool]
+    impl EmailTool {
+        /// Sends an email
+        /// `to` - Who to send the email to
+        #[tool_part]
+        fn send(&self, to: String) -> String {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            format!("Sent to {to}")
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_then_execute() {
+        let tool = EmailTool::default();
+        let sent = tool.sent.clone();
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(tool).unwrap();
+        let resolved = toolbox
+            .resolve(serde_json::json!({"function_name": "send", "parameters": {"to": "a@b.c"}}))
+            .unwrap();
+        assert_eq!(resolved.function_call().function_name(), "send");
+        assert_eq!(resolved.function_call().parameters()["to"], "a@b.c");
+        assert_eq!(resolved.tool().function_names(), &["send"]);
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+        assert_eq!(toolbox.execute(resolved).await.unwrap(), Ok("Sent to a@b.c".to_owned()));
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert!(toolbox
+            .resolve(serde_json::json!({"function_name": "delete", "parameters": {}}))
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod multi_line_description {
+    use llmtoolbox::Tool;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Gets the weather.
+        /// Only cities are supported.
+        ///
+        /// Example:
+        /// ```json
+        /// {"city": "Paris"}
+        /// `city` - not a parameter inside a code block
+        /// ```
+        /// `city` - The city to get the weather for
+        #[tool_part]
+        fn weather(&self, city: String) -> String {
+            format!("Sunny in {city}")
+        }
+
+        /// Ignored for the attribute
+        /// `city` - The city to get the time in
+        #[tool_part(description = "Gets the time.\n\nUse 24 hour time.")]
+        fn time(&self, city: String) -> String {
+            format!("Noon in {city}")
+        }
+    }
+
+    #[test]
+    fn descriptions_are_verbatim() {
+        type MyToolTrait = dyn Tool<String, std::convert::Infallible>;
+        let schema = MyToolTrait::schema(&MyTool);
+        assert_eq!(
+            schema["oneOf"][0]["description"],
+            "Gets the weather.\nOnly cities are supported.\n\nExample:\n```json\n{\"city\": \"Paris\"}\n`city` - not a parameter inside a code block\n```"
+        );
+        assert_eq!(
+            schema["oneOf"][0]["properties"]["parameters"]["properties"]["city"]["description"],
+            "The city to get the weather for"
+        );
+        assert_eq!(schema["oneOf"][1]["description"], "Gets the time.\n\nUse 24 hour time.");
+    }
+}
+
+#[cfg(test)]
+pub mod namespace {
+
+    #[derive(Debug)]
+    struct FileTool;
+
+    #[llmtool::tool(namespace = "fs")]
+    impl FileTool {
+        /// Searches the files
+        /// `query` - What to search for
+        #[tool_part]
+        fn search(&self, query: String) -> String {
+            format!("file {query}")
+        }
+    }
+
+    #[derive(Debug)]
+    struct WebTool;
+
+    #[llmtool::tool(namespace = "web")]
+    impl WebTool {
+        /// Searches the web
+        /// `query` - What to search for
+        #[tool_part]
+        fn search(&self, query: String) -> String {
+            format!("web {query}")
+        }
+    }
+
+    #[tokio::test]
+    async fn same_function_in_different_namespaces() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(FileTool).unwrap();
+        toolbox.add_tool(WebTool).unwrap();
+        assert_eq!(toolbox.schema()["oneOf"][0]["properties"]["function_name"]["const"], "fs__search");
+        assert_eq!(toolbox.schema()["oneOf"][1]["properties"]["function_name"]["const"], "web__search");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "web__search", "parameters": {"query": "rust"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "web rust");
+        let result = toolbox
+            .call_from_value(serde_json::json!({"function_name": "fs__search", "parameters": {"query": "rust"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "file rust");
+        assert!(toolbox
+            .call_from_value(serde_json::json!({"function_name": "search", "parameters": {"query": "rust"}}))
+            .await
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod default_parameter {
+    #[derive(Debug)]
+    struct SearchTool;
+
+    #[llmtool::tool]
+    impl SearchTool {
+        /// Searches the web
+        /// `query` - What to search for
+        /// `limit` - The most results to return
+        /// `language` - The language of the results
+        #[tool_part]
+        fn search(&self, query: &str, #[tool_param(default = 10)] limit: u32, #[tool_param(default = "en")] language: Option<String>) -> String {
+            format!("{query} {limit} {language:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_parameters_use_the_default() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(SearchTool).unwrap();
+        let parameters = &toolbox.schema()["oneOf"][0]["properties"]["parameters"];
+        assert_eq!(parameters["properties"]["limit"]["default"], 10);
+        assert_eq!(parameters["properties"]["language"]["default"], "en");
+        assert_eq!(parameters["required"], serde_json::json!(["query"]));
+
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "search", "parameters": {"query": "rust"}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("rust 10 Some(\"en\")".to_owned()));
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "search", "parameters": {"query": "rust", "limit": 3, "language": null}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("rust 3 None".to_owned()));
+    }
+}
+
+#[cfg(test)]
+pub mod different_tool_types {
+    #[derive(Debug)]
+    struct WeatherTool;
+
+    #[llmtool::tool]
+    impl WeatherTool {
+        /// Gets the weather
+        /// `city` - The city to get the weather for
+        #[tool_part]
+        fn weather(&self, city: &str) -> String {
+            format!("Sunny in {city}")
+        }
+    }
+
+    #[derive(Debug)]
+    struct ClockTool {
+        hour: u8,
+    }
+
+    #[llmtool::tool]
+    impl ClockTool {
+        /// Gets the time
+        #[tool_part]
+        fn time(&self) -> String {
+            format!("{}:00", self.hour)
+        }
+    }
+
+    #[tokio::test]
+    async fn share_one_toolbox() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(WeatherTool).unwrap();
+        toolbox
+            .add_boxed_tool(Box::new(ClockTool { hour: 9 }))
+            .unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "weather", "parameters": {"city": "Oslo"}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("Sunny in Oslo".to_owned()));
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "time", "parameters": {}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("9:00".to_owned()));
+
+        let mut local_toolbox: llmtoolbox::ToolBoxLocal<String, std::convert::Infallible> = llmtoolbox::ToolBoxLocal::new();
+        local_toolbox.add_tool(ClockTool { hour: 5 }).unwrap();
+        local_toolbox.add_boxed_tool(Box::new(WeatherTool)).unwrap();
+        assert_eq!(local_toolbox.len(), 2);
+        assert_eq!(local_toolbox.schema()["oneOf"].as_array().unwrap().len(), 2);
+    }
+}
+
+#[cfg(test)]
+pub mod json_value_parameter {
+    #[derive(Debug)]
+    struct ConfigTool;
+
+    #[llmtool::tool]
+    impl ConfigTool {
+        /// Forwards raw config
+        /// `config` - Any config
+        /// `extra` - Any extra config
+        #[tool_part]
+        fn forward(&self, config: serde_json::Value, extra: Option<serde_json::Value>) -> String {
+            format!("{config} {extra:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_any_json() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(ConfigTool).unwrap();
+        let parameters = &toolbox.schema()["oneOf"][0]["properties"]["parameters"];
+        assert_eq!(parameters["properties"]["config"], serde_json::json!({"description": "Any config"}));
+        assert_eq!(parameters["required"], serde_json::json!(["config"]));
+
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "forward", "parameters": {"config": {"a": [1, "b"]}}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("{\"a\":[1,\"b\"]} None".to_owned()));
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "forward", "parameters": {"config": 3, "extra": "x"}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("3 Some(String(\"x\"))".to_owned()));
+    }
+
+    mod own_value {
+        /// A measured value
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        pub struct Value {
+            pub amount: f64,
+        }
+
+        #[derive(Debug)]
+        pub struct MeasureTool;
+
+        #[llmtool::tool]
+        impl MeasureTool {
+            /// Records a measurement
+            /// `value` - The measurement
+            #[tool_part]
+            fn record(&self, value: Value) -> String {
+                value.amount.to_string()
+            }
+        }
+    }
+
+    #[test]
+    fn own_value_type_keeps_its_schema() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(own_value::MeasureTool).unwrap();
+        let value = &toolbox.schema()["oneOf"][0]["properties"]["parameters"]["properties"]["value"];
+        assert_eq!(value["type"], "object");
+        assert_eq!(value["properties"]["amount"]["type"], "number");
+    }
+}
+
+#[cfg(test)]
+pub mod map_parameter {
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug)]
+    struct SettingsTool;
+
+    #[llmtool::tool]
+    impl SettingsTool {
+        /// Applies settings
+        /// `limits` - The limit of each resource
+        /// `labels` - The labels to set
+        #[tool_part]
+        fn apply(&self, limits: HashMap<String, u32>, labels: Option<BTreeMap<String, String>>) -> String {
+            let mut limits: Vec<_> = limits.into_iter().collect();
+            limits.sort();
+            format!("{limits:?} {labels:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn inlines_the_value_type() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(SettingsTool).unwrap();
+        let parameters = &toolbox.schema()["oneOf"][0]["properties"]["parameters"];
+        assert_eq!(
+            parameters["properties"]["limits"],
+            serde_json::json!({
+                "type": "object",
+                "additionalProperties": { "type": "integer", "minimum": 0, "maximum": u32::MAX },
+                "description": "The limit of each resource"
+            })
+        );
+        assert_eq!(parameters["properties"]["labels"]["type"], serde_json::json!(["object", "null"]));
+        assert_eq!(parameters["properties"]["labels"]["additionalProperties"], serde_json::json!({"type": "string"}));
+        assert_eq!(parameters["required"], serde_json::json!(["limits"]));
+        assert!(parameters.get("definitions").is_none());
+
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "apply", "parameters": {"limits": {"cpu": 2, "memory": 512}, "labels": {"env": "prod"}}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("[(\"cpu\", 2), (\"memory\", 512)] Some({\"env\": \"prod\"})".to_owned()));
+    }
+
+    #[test]
+    fn strict_schema_keeps_maps_open() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(SettingsTool).unwrap();
+        let parameters = &toolbox.strict_openai_schema()[0]["function"]["parameters"];
+        assert_eq!(parameters["additionalProperties"], false);
+        assert_eq!(parameters["required"], serde_json::json!(["labels", "limits"]));
+        let limits = &parameters["properties"]["limits"];
+        assert_eq!(limits["additionalProperties"]["type"], "integer");
+        assert!(limits.get("properties").is_none());
+        let labels = &parameters["properties"]["labels"];
+        assert_eq!(labels["type"], serde_json::json!(["object", "null"]));
+        assert_eq!(labels["additionalProperties"], serde_json::json!({"type": "string"}));
+    }
+}
+
+#[cfg(test)]
+pub mod schema_cleaner {
+    use llmtoolbox::SchemaCleaner;
+    use serde_json::json;
+
+    #[test]
+    fn default_removes_schema_and_title() {
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Place",
+            "type": "object",
+            "properties": { "city": { "title": "City", "type": "string" } }
+        });
+        SchemaCleaner::default().clean(&mut schema);
+        assert_eq!(schema, json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } }
+        }));
+    }
+
+    #[test]
+    fn configured() {
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "place": { "$ref": "#/definitions/Place", "description": "Where" },
+                "tree": { "$ref": "#/$defs/Tree" }
+            },
+            "definitions": {
+                "Place": { "type": "object", "properties": { "city": { "type": "string" } }, "description": "A place" }
+            },
+            "$defs": {
+                "Tree": { "type": "object", "properties": { "children": { "type": "array", "items": { "$ref": "#/$defs/Tree" } } } }
+            }
+        });
+        SchemaCleaner::new()
+            .with_remove_keys(["$schema"])
+            .with_additional_properties_false(true)
+            .with_inline_refs(true)
+            .clean(&mut schema);
+        assert_eq!(schema["properties"]["place"], json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "description": "Where",
+            "additionalProperties": false
+        }));
+        // recursive references are kept, so they still resolve
+        assert_eq!(schema["properties"]["tree"], json!({ "$ref": "#/$defs/Tree" }));
+        assert_eq!(schema["$defs"]["Tree"]["properties"]["children"]["items"], json!({ "$ref": "#/$defs/Tree" }));
+        assert!(schema.get("definitions").is_none());
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(schema["additionalProperties"], false);
+    }
+}
+
+#[cfg(test)]
+pub mod multi_toolbox {
+    #[derive(Debug)]
+    struct LogPlugin;
+
+    #[llmtool::tool]
+    impl LogPlugin {
+        /// Handles an event
+        /// `event` - The event that happened
+        #[tool_part]
+        fn on_event(&self, event: &str) -> String {
+            format!("logged {event}")
+        }
+    }
+
+    #[derive(Debug)]
+    struct NotifyPlugin;
+
+    #[llmtool::tool]
+    impl NotifyPlugin {
+        /// Notifies about an event
+        /// `event` - The event to notify about
+        #[tool_part]
+        fn on_event(&self, event: &str) -> String {
+            format!("notified {event}")
+        }
+
+        /// Notifies everyone
+        #[tool_part]
+        fn notify_all(&self) -> String {
+            "notified everyone".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_every_matching_tool() {
+        let mut toolbox: llmtoolbox::MultiToolBox<String, std::convert::Infallible> = llmtoolbox::MultiToolBox::new();
+        toolbox.add_tool(LogPlugin);
+        toolbox.add_tool(NotifyPlugin);
+        assert_eq!(toolbox.len(), 2);
+        let function_schemas = toolbox.schema()["oneOf"].as_array().unwrap();
+        assert_eq!(function_schemas.len(), 2);
+        assert_eq!(function_schemas[0]["description"], "Handles an event");
+
+        let results: Vec<_> = toolbox
+            .call_all(serde_json::json!({"function_name": "on_event", "parameters": {"event": "start"}}))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, vec!["logged start", "notified start"]);
+        let results = toolbox
+            .call_all(serde_json::json!({"function_name": "notify_all", "parameters": {}}))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let error = toolbox
+            .call_all(serde_json::json!({"function_name": "on_exit", "parameters": {}}))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, llmtoolbox::FunctionCallError::FunctionNotFound { .. }));
+        let error = toolbox
+            .call_all(serde_json::json!({"function_name": "on_event", "parameters": {}}))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, llmtoolbox::FunctionCallError::Parsing { .. }));
+
+        let mut local_toolbox: llmtoolbox::MultiToolBoxLocal<String, std::convert::Infallible> = llmtoolbox::MultiToolBoxLocal::default();
+        local_toolbox.add_tool(NotifyPlugin);
+        local_toolbox.add_boxed_tool(Box::new(LogPlugin));
+        let results = local_toolbox
+            .call_all(serde_json::json!({"function_name": "on_event", "parameters": {"event": "stop"}}))
+            .await
+            .unwrap();
+        assert_eq!(results[1].as_ref().unwrap(), "logged stop");
+    }
+}
+
+#[cfg(test)]
+pub mod dynamic_schema {
+    use llmtoolbox::{FunctionCallError, Tool};
+    use serde_json::{json, Map, Value};
+
+    /// Reads one of the files found at startup
+    struct ReadTool {
+        files: Vec<String>,
+        schema: Map<String, Value>,
+    }
+
+    impl ReadTool {
+        fn new(files: Vec<String>) -> Self {
+            let schema = json!({
+                "oneOf": [{
+                    "type": "object",
+                    "properties": {
+                        "function_name": { "const": "read" },
+                        "description": "Reads a file",
+                        "parameters": {
+                            "type": "object",
+                            "properties": { "file": { "type": "string", "enum": files } },
+                            "required": ["file"]
+                        }
+                    }
+                }]
+            });
+            let Value::Object(schema) = schema else { unreachable!() };
+            Self { files, schema }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Tool<String, std::convert::Infallible> for ReadTool {
+        fn function_names(&self) -> &[&'static str] {
+            &["read"]
+        }
+
+        fn descriptions(&self) -> &[&'static str] {
+            &["Reads a file"]
+        }
+
+        fn schema(&self) -> &Map<String, Value> {
+            &self.schema
+        }
+
+        async fn call_function(
+            &self,
+            name: &str,
+            parameters: Map<String, Value>,
+        ) -> Result<Result<String, std::convert::Infallible>, FunctionCallError> {
+            if name != "read" {
+                return Err(FunctionCallError::function_not_found(name.to_owned()));
+            }
+            match parameters.get("file").and_then(Value::as_str) {
+                Some(file) if self.files.iter().any(|known| known == file) => Ok(Ok(format!("contents of {file}"))),
+                _ => Err(FunctionCallError::parsing("`file` is not a known file".to_owned())),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct ClockTool;
+
+    #[llmtool::tool]
+    impl ClockTool {
+        /// Gets the time
+        #[tool_part]
+        fn time(&self) -> String {
+            "9:00".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_schema_alongside_static_schema() {
+        let files = vec!["a.txt".to_owned(), "b.txt".to_owned()];
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(ReadTool::new(files)).unwrap();
+        toolbox.add_tool(ClockTool).unwrap();
+        let read = toolbox.function_schema("read").unwrap();
+        assert_eq!(read["properties"]["parameters"]["properties"]["file"]["enum"], json!(["a.txt", "b.txt"]));
+        assert!(toolbox.function_schema("time").is_some());
+
+        let output = toolbox
+            .call_from_value(json!({"function_name": "read", "parameters": {"file": "b.txt"}}))
+            .await
+            .unwrap();
+        assert_eq!(output, Ok("contents of b.txt".to_owned()));
+    }
+}
+
+#[cfg(test)]
+pub mod custom_output_trait {
+    pub trait Summary {
+        fn summary(&self) -> String;
+    }
+
+    pub struct Weather {
+        city: String,
+    }
+
+    impl Summary for Weather {
+        fn summary(&self) -> String {
+            format!("Sunny in {}", self.city)
+        }
+    }
+
+    impl Summary for u32 {
+        fn summary(&self) -> String {
+            format!("{self} results")
+        }
+    }
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool(output = Box<dyn Summary>)]
+    impl MyTool {
+        /// Gets the weather
+        /// `city` - The city to get the weather for
+        #[tool_part]
+        fn weather(&self, city: String) -> Weather {
+            Weather { city }
+        }
+
+        /// Counts the results
+        #[tool_part]
+        fn count(&self) -> u32 {
+            3
+        }
+
+        /// Summarizes already
+        #[tool_part]
+        fn boxed(&self) -> Box<dyn Summary> {
+            Box::new(7u32)
+        }
+    }
+
+    #[tokio::test]
+    async fn outputs_are_the_declared_trait_object() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn Summary>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let mut summaries = Vec::new();
+        for function_call in [
+            serde_json::json!({"function_name": "weather", "parameters": {"city": "Oslo"}}),
+            serde_json::json!({"function_name": "count", "parameters": {}}),
+            serde_json::json!({"function_name": "boxed", "parameters": {}}),
+        ] {
+            let output = toolbox.call_from_value(function_call).await.unwrap().unwrap();
+            summaries.push(output.summary());
+        }
+        assert_eq!(summaries, vec!["Sunny in Oslo", "3 results", "7 results"]);
+
+        // the `Any` output is still available
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "count", "parameters": {}}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*output.downcast::<u32>().unwrap(), 3);
+    }
+}
+
+#[cfg(test)]
+pub mod qualified_result {
+    use std::io;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Parses a number
+        /// `text` - The text to parse
+        #[tool_part]
+        fn parse(&self, text: String) -> anyhow::Result<u32> {
+            Ok(text.parse()?)
+        }
+
+        /// Halves an even number
+        /// `number` - The number to halve
+        #[tool_part]
+        fn halve(&self, number: u32) -> anyhow::Result<u32> {
+            anyhow::ensure!(number.is_multiple_of(2), "{number} is odd");
+            Ok(number / 2)
+        }
+    }
+
+    #[derive(Debug)]
+    struct StdTool;
+
+    #[llmtool::tool]
+    impl StdTool {
+        /// Reads a setting
+        #[tool_part]
+        fn read(&self) -> std::result::Result<String, std::io::Error> {
+            Err(std::io::Error::other("no settings"))
+        }
+
+        /// Writes a setting
+        #[tool_part]
+        fn write(&self) -> std::io::Result<u32> {
+            Ok(1)
+        }
+    }
+
+    #[derive(Debug)]
+    struct FmtTool;
+
+    #[llmtool::tool]
+    impl FmtTool {
+        /// Formats a number
+        /// `number` - The number to format
+        #[tool_part]
+        fn format(&self, number: u32) -> std::fmt::Result {
+            if number == 0 {
+                return Err(std::fmt::Error);
+            }
+            Ok(())
+        }
+
+        /// Reads a file
+        #[tool_part]
+        fn read_file(&self) -> io::Result<String> {
+            Ok("contents".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn anyhow_result() {
+        let mut toolbox: llmtoolbox::ToolBox<u32, anyhow::Error> = llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "parse", "parameters": {"text": "12"}}))
+            .await
+            .unwrap();
+        assert_eq!(output.unwrap(), 12);
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "halve", "parameters": {"number": 3}}))
+            .await
+            .unwrap();
+        assert_eq!(output.unwrap_err().to_string(), "3 is odd");
+
+        let mut toolbox: llmtoolbox::ToolBox<u32, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "parse", "parameters": {"text": "x"}}))
+            .await
+            .unwrap();
+        assert_eq!(output.unwrap_err().to_string(), "invalid digit found in string");
+    }
+
+    #[tokio::test]
+    async fn std_result() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, std::io::Error> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(StdTool).unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "read", "parameters": {}}))
+            .await
+            .unwrap();
+        assert_eq!(output.unwrap_err().to_string(), "no settings");
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "write", "parameters": {}}))
+            .await
+            .unwrap();
+        assert_eq!(*output.unwrap().downcast::<u32>().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn fmt_and_io_result() {
+        let mut toolbox: llmtoolbox::ToolBox<Box<dyn std::any::Any>, Box<dyn std::error::Error>> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(FmtTool).unwrap();
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "format", "parameters": {"number": 0}}))
+            .await
+            .unwrap();
+        assert!(output.unwrap_err().is::<std::fmt::Error>());
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "format", "parameters": {"number": 1}}))
+            .await
+            .unwrap();
+        assert!(output.unwrap().is::<()>());
+        let output = toolbox
+            .call_from_value(serde_json::json!({"function_name": "read_file", "parameters": {}}))
+            .await
+            .unwrap();
+        assert_eq!(*output.unwrap().downcast::<String>().unwrap(), "contents");
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+pub mod stats {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Greet
+        #[tool_part]
+        async fn hello(&self) -> String {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            "Hello".to_owned()
+        }
+
+        /// Greet
+        #[tool_part]
+        fn goodbye(&self) -> String {
+            "Goodbye".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn records_calls() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        for _ in 0..2 {
+            let result = toolbox
+                .call_from_value(serde_json::json!({"function_name": "hello", "parameters": {}}))
+                .await;
+            assert!(matches!(result, Ok(Ok(_))));
+        }
+        let stats = toolbox.stats();
+        assert_eq!(stats["hello"].count, 2);
+        assert!(stats["hello"].max >= std::time::Duration::from_millis(5));
+        assert!(stats["hello"].total >= stats["hello"].max);
+        assert_eq!(stats["goodbye"].count, 0);
+    }
+}
+
+#[cfg(all(test, feature = "validation"))]
+pub mod validation {
+    use llmtoolbox::FunctionCallError;
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Repeats a word
+        /// `word` - the word to repeat
+        /// `times` - how many times
+        #[tool_part]
+        fn repeat(&self, word: String, times: u32) -> String {
+            word.repeat(times as usize)
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_parameters() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+
+        let result = toolbox
+            .call_from_value_validated(serde_json::json!({"function_name": "repeat", "parameters": {"word": "a", "times": 2}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "aa");
+
+        let result = toolbox
+            .call_from_value_validated(serde_json::json!({"function_name": "repeat", "parameters": {"word": "a"}}))
+            .await;
+        assert!(matches!(result, Err(FunctionCallError::SchemaViolation { .. })));
+
+        let result = toolbox
+            .call_from_value_validated(serde_json::json!({"function_name": "repeat", "parameters": {"word": "a", "times": "two"}}))
+            .await;
+        match result {
+            Err(FunctionCallError::SchemaViolation { path, .. }) => assert_eq!(path, "/times"),
+            _ => panic!("expected a schema violation"),
+        }
+    }
+
+    #[tokio::test]
+    async fn validators_follow_the_tools() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let invalid = serde_json::json!({"function_name": "repeat", "parameters": {"word": "a"}});
+        for _ in 0..2 {
+            let result = toolbox.call_from_value_validated(invalid.clone()).await;
+            assert!(matches!(result, Err(FunctionCallError::SchemaViolation { .. })));
+        }
+        assert!(toolbox.remove_tool("repeat"));
+        let result = toolbox.call_from_value_validated(invalid.clone()).await;
+        assert!(matches!(result, Err(FunctionCallError::FunctionNotFound { .. })));
+        toolbox.add_tool(MyTool).unwrap();
+        let result = toolbox.call_from_value_validated(invalid).await;
+        assert!(matches!(result, Err(FunctionCallError::SchemaViolation { .. })));
+    }
+
+    #[test]
+    fn toolbox_schema_is_valid() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let schema = serde_json::Value::Object(toolbox.schema().clone());
+        llmtoolbox::assert_valid_schema(&schema).unwrap();
+
+        let errors = llmtoolbox::assert_valid_schema(&serde_json::json!({
+            "type": "object",
+            "required": "word"
+        }))
+        .unwrap_err();
+        match &errors[..] {
+            [llmtoolbox::FunctionCallParsingError::SchemaViolation { path, .. }] => assert_eq!(path, "/required"),
+            _ => panic!("expected a schema violation"),
+        }
+        let errors = llmtoolbox::assert_valid_schema(&serde_json::json!({
+            "oneOf": [{
+                "properties": {
+                    "function_name": { "const": "repeat" },
+                    "parameters": { "properties": { "word": { "$ref": "#/definitions/Word" } } }
+                }
+            }]
+        }))
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("`repeat`"));
+        // the reference resolves in the parameter schema, but not from the root of the document
+        let errors = llmtoolbox::assert_valid_schema(&serde_json::json!({
+            "oneOf": [{
+                "properties": {
+                    "function_name": { "const": "repeat" },
+                    "parameters": {
+                        "properties": { "word": { "$ref": "#/definitions/Word" } },
+                        "definitions": { "Word": { "type": "string" } }
+                    }
+                }
+            }]
+        }))
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+}
+
+#[cfg(all(test, feature = "mcp"))]
+pub mod mcp {
+
+    #[derive(Debug)]
+    struct MyTool;
+
+    #[llmtool::tool]
+    impl MyTool {
+        /// Greets someone
+        /// `name` - who to greet
+        #[tool_part]
+        fn greet(&self, name: String) -> String {
+            format!("Hello {name}")
+        }
+
+        /// Greets everyone
+        #[tool_part]
+        fn greet_all(&self) -> String {
+            "Hello everyone".to_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_and_calls_tools() {
+        let mut toolbox: llmtoolbox::ToolBox<String, std::convert::Infallible> =
+            llmtoolbox::ToolBox::new();
+        toolbox.add_tool(MyTool).unwrap();
+        let tool_list = toolbox.mcp_tool_list();
+        assert_eq!(tool_list.len(), 2);
+        assert_eq!(tool_list[0]["name"], "greet");
+        assert_eq!(tool_list[0]["description"], "Greets someone");
+        assert_eq!(tool_list[0]["inputSchema"]["type"], "object");
+        assert_eq!(tool_list[0]["inputSchema"]["required"], serde_json::json!(["name"]));
+        assert_eq!(tool_list[1]["inputSchema"]["properties"], serde_json::json!({}));
+
+        let result = toolbox
+            .call_from_mcp(serde_json::json!({"name": "greet", "arguments": {"name": "Ferris"}}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Hello Ferris");
+        let result = toolbox
+            .call_from_mcp(serde_json::json!({"name": "greet_all"}))
+            .await;
+        assert_eq!(result.unwrap().unwrap(), "Hello everyone");
+    }
+}